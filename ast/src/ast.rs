@@ -34,7 +34,7 @@ pub enum PlayerExpr {
 pub enum IntExpr {
     Int(i32),
     IntOp(Box<IntExpr>, Op, Box<IntExpr>),
-    IntCollectionAt(Box<IntExpr>),
+    IntCollectionAt(IntCollection, Box<IntExpr>),
     SizeOf(Collection),
     SumOfIntCollection(IntCollection),
     SumOfCardSet(Box<CardSet>, PointMap),
@@ -43,6 +43,8 @@ pub enum IntExpr {
     MinIntCollection(IntCollection),
     MaxIntCollection(IntCollection),
     StageRoundCounter,
+    Memory(Memory),
+    Demanded,
     // PlayRoundCounter,
 }
 
@@ -100,6 +102,7 @@ pub enum StringExpr {
     ID(ID),
     KeyOf(Key, CardPosition),
     StringCollectionAt(StringCollection, IntExpr),
+    Demanded,
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -111,6 +114,7 @@ pub enum CardPosition {
     MinPrec (Box<CardSet>, Precedence),
     MaxPoint(Box<CardSet>, PointMap),
     MinPoint(Box<CardSet>, PointMap),
+    Demanded,
 }
 
 #[derive(Debug, PartialEq, Clone)]
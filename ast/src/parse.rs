@@ -19,6 +19,7 @@ mod kw {
   syn::custom_keyword!(turn);
   syn::custom_keyword!(winner);
   syn::custom_keyword!(demand);
+  syn::custom_keyword!(demanded);
   syn::custom_keyword!(cycle);
   syn::custom_keyword!(bid);
   syn::custom_keyword!(successful);
@@ -296,6 +297,11 @@ impl Parse for TeamExpr {
 
 impl Parse for CardPosition {
   fn parse(input: ParseStream) -> Result<Self> {
+      if input.peek(kw::demanded) {
+        input.parse::<kw::demanded>()?;
+
+        return Ok(CardPosition::Demanded)
+      }
       if input.peek(kw::top) {
         input.parse::<kw::top>()?;
         
@@ -398,6 +404,17 @@ impl Parse for CardPosition {
 
 impl Parse for IntExpr {
   fn parse(input: ParseStream) -> Result<Self> {
+      // only a parenthesized group followed by brackets is an IntCollectionAt,
+      // forking for every group would parse nested groups over and over
+      if input.peek(syn::token::Paren) && input.peek2(syn::token::Bracket) {
+        let int_collection = input.parse::<IntCollection>()?;
+
+        let content;
+        bracketed!(content in input);
+        let int = content.parse::<IntExpr>()?;
+
+        return Ok(IntExpr::IntCollectionAt(int_collection, Box::new(int)))
+      }
       if input.peek(syn::token::Paren) {
         let content;
         parenthesized!(content in input);
@@ -471,6 +488,18 @@ impl Parse for IntExpr {
 
         return Ok(IntExpr::StageRoundCounter)
       }
+      if input.peek(kw::memory) {
+        input.parse::<kw::memory>()?;
+
+        let memory = input.parse::<Memory>()?;
+
+        return Ok(IntExpr::Memory(memory))
+      }
+      if input.peek(kw::demanded) {
+        input.parse::<kw::demanded>()?;
+
+        return Ok(IntExpr::Demanded)
+      }
 
       let int: i32 = (input.parse::<LitInt>()?).base10_parse()?;
       
@@ -703,6 +732,11 @@ fn parse_outof_collection(input: ParseStream) -> Result<BoolExpr> {
 // StringExpr ================================================================
 impl Parse for StringExpr {
     fn parse(input: ParseStream) -> Result<Self> {
+        if input.peek(kw::demanded) && !input.peek2(kw::of) {
+            input.parse::<kw::demanded>()?;
+            return Ok(StringExpr::Demanded);
+        }
+
         if let Ok(expr) = try_parse_keyof(input) {
             return Ok(expr);
         }
@@ -1351,15 +1385,17 @@ impl Parse for ClassicMove {
 
       let fork = input.fork();
       if let Ok(quantity) = fork.parse::<Quantity>() {
-        input.advance_to(&fork);
+        if fork.peek(kw::from) {
+          input.advance_to(&fork);
 
-        input.parse::<kw::from>()?;
-        let from_cardset = input.parse::<CardSet>()?;
-        let status = input.parse::<Status>()?;
-        input.parse::<kw::to>()?;
-        let to_cardset = input.parse::<CardSet>()?;
+          input.parse::<kw::from>()?;
+          let from_cardset = input.parse::<CardSet>()?;
+          let status = input.parse::<Status>()?;
+          input.parse::<kw::to>()?;
+          let to_cardset = input.parse::<CardSet>()?;
 
-        return Ok(ClassicMove::MoveQuantity(quantity, from_cardset, status, to_cardset))
+          return Ok(ClassicMove::MoveQuantity(quantity, from_cardset, status, to_cardset))
+        }
       }
 
       let from_cardset = input.parse::<CardSet>()?;
@@ -1377,15 +1413,17 @@ impl Parse for DealMove {
 
       let fork = input.fork();
       if let Ok(quantity) = fork.parse::<Quantity>() {
-        input.advance_to(&fork);
+        if fork.peek(kw::from) {
+          input.advance_to(&fork);
 
-        input.parse::<kw::from>()?;
-        let from_cardset = input.parse::<CardSet>()?;
-        let status = input.parse::<Status>()?;
-        input.parse::<kw::to>()?;
-        let to_cardset = input.parse::<CardSet>()?;
+          input.parse::<kw::from>()?;
+          let from_cardset = input.parse::<CardSet>()?;
+          let status = input.parse::<Status>()?;
+          input.parse::<kw::to>()?;
+          let to_cardset = input.parse::<CardSet>()?;
 
-        return Ok(DealMove::DealQuantity(quantity, from_cardset, status, to_cardset))
+          return Ok(DealMove::DealQuantity(quantity, from_cardset, status, to_cardset))
+        }
       }
 
       let from_cardset = input.parse::<CardSet>()?;
@@ -1403,16 +1441,18 @@ impl Parse for ExchangeMove {
 
       let fork = input.fork();
       if let Ok(quantity) = fork.parse::<Quantity>() {
-        input.advance_to(&fork);
+        if fork.peek(kw::from) {
+          input.advance_to(&fork);
 
-        input.parse::<kw::from>()?;
+          input.parse::<kw::from>()?;
         
-        let from_cardset = input.parse::<CardSet>()?;
-        let status = input.parse::<Status>()?;
-        input.parse::<kw::with>()?;
-        let to_cardset = input.parse::<CardSet>()?;
+          let from_cardset = input.parse::<CardSet>()?;
+          let status = input.parse::<Status>()?;
+          input.parse::<kw::with>()?;
+          let to_cardset = input.parse::<CardSet>()?;
 
-        return Ok(ExchangeMove::ExchangeQuantity(quantity, from_cardset, status, to_cardset))
+          return Ok(ExchangeMove::ExchangeQuantity(quantity, from_cardset, status, to_cardset))
+        }
       }
 
       let from_cardset = input.parse::<CardSet>()?;
@@ -1486,14 +1526,16 @@ impl Parse for TokenMove {
 
       let fork = input.fork();
       if let Ok(quantity) = fork.parse::<Quantity>() {
-        input.advance_to(&fork);
+        if fork.peek(kw::from) {
+          input.advance_to(&fork);
 
-        input.parse::<kw::from>()?;
-        let from_tokenloc = input.parse::<TokenLocExpr>()?;
-        input.parse::<kw::to>()?;
-        let to_tokenloc = input.parse::<TokenLocExpr>()?;
+          input.parse::<kw::from>()?;
+          let from_tokenloc = input.parse::<TokenLocExpr>()?;
+          input.parse::<kw::to>()?;
+          let to_tokenloc = input.parse::<TokenLocExpr>()?;
 
-        return Ok(TokenMove::PlaceQuantity(quantity, from_tokenloc, to_tokenloc))
+          return Ok(TokenMove::PlaceQuantity(quantity, from_tokenloc, to_tokenloc))
+        }
       }
 
       let from_tokenloc = input.parse::<TokenLocExpr>()?;
@@ -245,6 +245,14 @@ mod tests {
         assert_eq!(parsed, CardPosition::Bottom(format_ident!("hand")));
     }
 
+    #[test]
+    fn parses_valid_cardposition_demanded() {
+        let parsed: CardPosition = parse_str(
+          "demanded"
+        ).unwrap();
+        assert_eq!(parsed, CardPosition::Demanded);
+    }
+
     #[test]
     fn parses_valid_cardposition_max_of_using_prec() {
         let parsed: CardPosition = parse_str(
@@ -383,6 +391,32 @@ mod tests {
         );
     }
     
+    #[test]
+    fn parses_valid_intexpr_intcollection_at() {
+        let parsed: IntExpr = parse_str(
+          "(3, 4, 5)[1]"
+        ).unwrap();
+        assert_eq!(parsed, IntExpr::IntCollectionAt(
+          IntCollection {
+            ints: vec![IntExpr::Int(3), IntExpr::Int(4), IntExpr::Int(5)]
+          },
+          Box::new(IntExpr::Int(1))
+        ));
+    }
+
+    #[test]
+    fn parses_valid_intexpr_nested_intop() {
+        let depth = 30;
+        let source = format!("{}1{}", "(".repeat(depth), " + 1)".repeat(depth));
+        let parsed: IntExpr = parse_str(&source).unwrap();
+
+        let mut expected = IntExpr::Int(1);
+        for _ in 0..depth {
+          expected = IntExpr::IntOp(Box::new(expected), Op::Plus, Box::new(IntExpr::Int(1)));
+        }
+        assert_eq!(parsed, expected);
+    }
+
     #[test]
     fn parses_valid_intexpr_stageroundcounter() {
         let parsed: IntExpr = parse_str(
@@ -391,6 +425,22 @@ mod tests {
         assert_eq!(parsed, IntExpr::StageRoundCounter);
     }
 
+    #[test]
+    fn parses_valid_intexpr_memory() {
+        let parsed: IntExpr = parse_str(
+          "memory Bid"
+        ).unwrap();
+        assert_eq!(parsed, IntExpr::Memory(format_ident!("Bid")));
+    }
+
+    #[test]
+    fn parses_valid_intexpr_demanded() {
+        let parsed: IntExpr = parse_str(
+          "demanded"
+        ).unwrap();
+        assert_eq!(parsed, IntExpr::Demanded);
+    }
+
     // =======================================================================
 
     // BoolExpr ==============================================================
@@ -597,6 +647,22 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn parses_valid_stringexpr_demanded() {
+        let parsed: StringExpr = parse_str(
+          "demanded"
+        ).unwrap();
+        assert_eq!(parsed, StringExpr::Demanded);
+
+        let parsed: StringExpr = parse_str(
+          "rank of demanded"
+        ).unwrap();
+        assert_eq!(parsed, StringExpr::KeyOf(
+          format_ident!("rank"),
+          CardPosition::Demanded
+        ));
+    }
+
     #[test]
     fn parses_valid_stringexpr_collection_at() {
         let parsed: StringExpr = parse_str(
@@ -1486,6 +1552,20 @@ mod tests {
         );
     }
 
+    #[test]
+    fn parses_valid_classicmove_move_demanded() {
+        let parsed: ClassicMove = parse_str(
+          "move demanded face up to discard"
+        ).unwrap();
+        assert_eq!(parsed,
+          ClassicMove::Move(
+            CardSet::Group(Group::CardPosition(CardPosition::Demanded)),
+            Status::FaceUp,
+            CardSet::Group(Group::Location(format_ident!("discard")))
+          )
+        );
+    }
+
     #[test]
     fn parses_valid_classicmove_move_quantity() {
         let parsed: ClassicMove = parse_str(
@@ -1,12 +1,15 @@
-use std::{collections::HashMap};
+use std::{collections::{HashMap, HashSet}, fmt};
 use ast::ast::*;
 
+use crate::hierarchy::*;
+
 pub type StateID = i32;
 pub type StageExit = i32;
 pub type ChoiceExit = i32;
 pub type TransitionID = i32;
+pub type StageID = i32;
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum Transition {
   Action(Rule),
   Condition(BoolExpr),
@@ -16,112 +19,286 @@ pub enum Transition {
   StageCounter,
   Optional,
   Choice,
+  /// Starts a new round count of the stage, the only way into a stage.
+  EnterStage(StageID),
+  /// Leaves the stage, after its EndCondition holds or by `end stage`.
+  ExitStage(StageID),
 }
 
-#[derive(Clone, Debug)]
+/// A SeqStage of the game.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StageInfo {
+  pub name: String,
+  /// The player the stage is played for.
+  pub player: PlayerExpr,
+  /// Number of enclosing stages, 0 for a stage that is not nested.
+  pub depth: usize,
+  pub parent: Option<StageID>,
+  /// The state that checks the EndCondition, every round starts there.
+  pub entry: StateID,
+  /// The state the ExitStage transition starts at.
+  pub exit: StateID,
+}
+
+#[derive(Clone, Debug, PartialEq)]
 pub struct FSM {
   pub states: HashMap<StateID, Vec<(TransitionID, StateID)>>,
   pub transitions: HashMap<TransitionID, Transition>,
   pub entry: StateID,
+  /// The states the game is over at: the end of the flows and every `end game with winner`.
+  /// Every other state has an outgoing transition.
   pub goals: Vec<StateID>,
+  pub stages: HashMap<StageID, StageInfo>,
+  /// The innermost stage of every state inside of a stage.
+  pub state_stages: HashMap<StateID, StageID>,
+  /// The innermost stage of every transition that starts inside of a stage.
+  pub transition_stages: HashMap<TransitionID, StageID>,
+}
+
+/// What went wrong while building the FSM.
+#[derive(Clone, Debug, PartialEq)]
+pub enum BuildErrorKind {
+  /// `end stage` is not inside of a stage.
+  EndStageOutsideStage,
+  /// A transition starts at a state that was never added.
+  UnknownState(StateID),
+  /// The game gets stuck at the state: it has no outgoing transition, but is neither the end of the flows
+  /// nor reached by `end game with winner`.
+  DeadEnd(StateID),
+}
+
+/// An error of a FlowComponent of the game.
+/// The AST has no source spans, the component is found by its path instead:
+/// the index in `Game::flows`, then the index in the flows (or options) of every enclosing component.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BuildError {
+  pub kind: BuildErrorKind,
+  pub path: Vec<usize>,
+}
+
+impl fmt::Display for BuildError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let path = self.path
+      .iter()
+      .map(|index| index.to_string())
+      .collect::<Vec<_>>()
+      .join(".");
+
+    match &self.kind {
+      BuildErrorKind::EndStageOutsideStage => write!(f, "flow {}: end stage outside of a stage", path),
+      BuildErrorKind::UnknownState(state) => write!(f, "flow {}: state {} does not exist", path, state),
+      BuildErrorKind::DeadEnd(state) => write!(f, "flow {}: the game gets stuck at state {}", path, state),
+    }
+  }
 }
 
+impl std::error::Error for BuildError {}
+
 impl Default for FSM {
   fn default() -> Self {
       FSM {
         states: HashMap::new(),
         transitions: HashMap::new(),
         entry: 0,
-        goals: Vec::new()
+        goals: Vec::new(),
+        stages: HashMap::new(),
+        state_stages: HashMap::new(),
+        transition_stages: HashMap::new(),
       }
   }
 }
 
 impl FSM {
-  /// Adds a transition to the current FSM.
-  fn add_transition(
-    &mut self,
-    from_state: StateID,
-    to_state: StateID,
-    transition_id: TransitionID,
-    transition: Transition
-  ) {
-    let transitions = self.states.get_mut(&from_state).expect("State could not been found!");
-    transitions.push((transition_id, to_state));
-
-    self.transitions.insert(transition_id, transition);
+  /// The innermost stage the state belongs to.
+  pub fn stage_of_state(&self, state: StateID) -> Option<&StageInfo> {
+    self.state_stages
+      .get(&state)
+      .and_then(|stage| self.stages.get(stage))
+  }
+
+  /// The innermost stage the transition belongs to.
+  pub fn stage_of_transition(&self, transition: TransitionID) -> Option<&StageInfo> {
+    self.transition_stages
+      .get(&transition)
+      .and_then(|stage| self.stages.get(stage))
   }
 
-  /// Adds a state to the current FSM.
-  fn add_state(&mut self, state_id: StateID) {
-    self.states.insert(state_id, Vec::new());
+  /// Whether the game is over at the state.
+  pub fn is_goal(&self, state: StateID) -> bool {
+    self.goals.contains(&state)
   }
 }
 
 pub struct FSMBuilder {
-  fsm: FSM,
+  /// The top level, the goals and the stages of the game that is being built.
+  hierarchy: HierarchicalFSM,
+  /// The machines of the stages that are being built, the innermost one is the last one.
+  /// A machine is added to the children of its parent once its stage is built.
+  machines: Vec<Machine>,
   current_state_id: i32,
   state_counter: i32,
   current_transition_id: i32,
   stage_exits: Vec<StageExit>,
+  /// States that end the turn in the stages that are being built.
+  /// They are connected to the end of the round once the body of the stage is built.
+  turn_ends: Vec<Vec<StateID>>,
   choice_exits: Vec<i32>,
+  /// Path of the FlowComponent that is being built.
+  path: Vec<usize>,
+  /// Path of the FlowComponent every state was added for, to report dead ends.
+  state_paths: HashMap<StateID, Vec<usize>>,
+  errors: Vec<BuildError>,
 }
 
 impl Default for FSMBuilder {
   fn default() -> Self {
     FSMBuilder {
-      fsm: FSM::default(),
+      hierarchy: HierarchicalFSM::default(),
+      machines: Vec::new(),
       current_state_id: 0,
       state_counter: 0,
       current_transition_id: 0,
       stage_exits: Vec::new(),
+      turn_ends: Vec::new(),
       choice_exits: Vec::new(),
+      path: Vec::new(),
+      state_paths: HashMap::new(),
+      errors: Vec::new(),
     }
   }
 }
 
 impl FSMBuilder {
   /// Builds FSM.
-  /// Initializes the first state and then continues with the building of the FlowComponent's
-  pub fn build_fsm(&mut self, game: Game) -> FSM {
+  /// Composes the machines of `build_hierarchical` into the flat FSM.
+  /// Returns every error of the game instead of stopping at the first one.
+  pub fn build_fsm(&mut self, game: Game) -> Result<FSM, Vec<BuildError>> {
+    let hierarchy = self.build_hierarchical(game)?;
+
+    return Ok(hierarchy.flatten())
+  }
+
+  /// Builds the FSM with one machine per SeqStage.
+  /// Initializes the first state and then continues with the building of the FlowComponent's,
+  /// every SeqStage is built into a machine of its own.
+  pub fn build_hierarchical(&mut self, game: Game) -> Result<HierarchicalFSM, Vec<BuildError>> {
     // initialize first state
-    self.fsm.add_state(self.current_state_id);
+    self.hierarchy.root.entry = self.current_state_id;
+    self.hierarchy.root.add_state(self.current_state_id);
 
     self.build_flows(&game.flows);
+    self.add_goals();
+
+    if !self.errors.is_empty() {
+      return Err(std::mem::take(&mut self.errors))
+    }
 
-    return self.fsm.clone()
+    return Ok(self.hierarchy.clone())
   }
 
   /// Takes a Vector of FlowComponent's and extends the FSM with them.
   fn build_flows(&mut self, flows: &Vec<FlowComponent>) {
-    for flow in flows.iter() {
+    for (index, flow) in flows.iter().enumerate() {
+      self.path.push(index);
       self.build_flow(flow);
+      self.path.pop();
     }
   }
 
+  /// Makes the end of the flows a goal, next to the states `end game with winner` leads to.
+  /// States the entry does not reach are removed first, with their transitions:
+  /// they only continue the flows after `end stage`, `end turn` or `end game`, and can never be played.
+  /// Any other state without an outgoing transition would stop the game without an end, it is an error.
+  fn add_goals(&mut self) {
+    self.hierarchy.goals.push(self.current_state_id);
+
+    let root = &self.hierarchy.root;
+    let states = root.machines()
+      .into_iter()
+      .flat_map(|machine| machine.states.iter())
+      .collect::<HashMap<_, _>>();
+
+    let mut reachable = HashSet::from([root.entry]);
+    let mut queue = vec![root.entry];
+    while let Some(state) = queue.pop() {
+      for (_, to_state) in states.get(&state).into_iter().copied().flatten() {
+        if reachable.insert(*to_state) {
+          queue.push(*to_state);
+        }
+      }
+    }
+
+    let mut dead_ends = states
+      .iter()
+      .filter(|(state, edges)| edges.is_empty() && reachable.contains(state))
+      .filter(|(state, _)| !self.hierarchy.goals.contains(state))
+      .map(|(state, _)| **state)
+      .collect::<Vec<_>>();
+    dead_ends.sort();
+
+    self.hierarchy.root.retain_states(&reachable);
+    self.hierarchy.goals.retain(|goal| reachable.contains(goal));
+
+    for state in dead_ends {
+      self.errors.push(
+        BuildError {
+          kind: BuildErrorKind::DeadEnd(state),
+          path: self.state_paths.get(&state).cloned().unwrap_or_default(),
+        }
+      );
+    }
+  }
+
+  /// Records an error of the FlowComponent that is being built.
+  fn error(&mut self, kind: BuildErrorKind) {
+    self.errors.push(
+      BuildError {
+        kind,
+        path: self.path.clone(),
+      }
+    );
+  }
+
   /// Increments the state_counter.
   /// Adds the the new state (id of state == state_counter) to the FSM.
   /// Sets current_state_id to state_counter.
   /// Returns updated state_counter.
   fn new_state(&mut self) -> i32 {
     self.state_counter += 1;
-    self.fsm.add_state(self.state_counter);
-    self.current_state_id = self.state_counter;
+    let state = self.state_counter;
+    self.machine().add_state(state);
+    self.state_paths.insert(state, self.path.clone());
+    self.current_state_id = state;
 
     return self.state_counter
   }
 
+  /// The machine of the innermost stage that is being built.
+  fn machine(&mut self) -> &mut Machine {
+    match self.machines.last_mut() {
+      Some(machine) => machine,
+      None => &mut self.hierarchy.root,
+    }
+  }
+
   /// Updates the current_transition_id.
-  /// Adds the transition to the FSM.
+  /// Adds the transition to the machine of the state it starts at.
   fn new_transition(&mut self, from_state: StateID, to_state: StateID, transition: Transition) {
     self.current_transition_id += 1;
 
-    self.fsm.add_transition(
+    let machine = match self.machines.iter().rposition(|machine| machine.states.contains_key(&from_state)) {
+      Some(index) => &mut self.machines[index],
+      None => &mut self.hierarchy.root,
+    };
+    let added = machine.add_transition(
       from_state,
       to_state,
       self.current_transition_id,
       transition
     );
+    if let Err(kind) = added {
+      self.error(kind);
+    }
   }
 
   /// Checks if we are in a ChoiceRule.
@@ -166,7 +343,7 @@ impl FSMBuilder {
     // start new "choice-block"
     self.choice_exits.push(exit);
 
-    for option in choice_rule.options.iter() {
+    for (index, option) in choice_rule.options.iter().enumerate() {
       let choice = self.new_state();
 
       self.new_transition(
@@ -175,7 +352,9 @@ impl FSMBuilder {
         Transition::Choice
       );
 
+      self.path.push(index);
       self.build_flow(option);
+      self.path.pop();
     }
 
     // end choice block
@@ -187,15 +366,52 @@ impl FSMBuilder {
   }
 
   fn build_seq_stage(&mut self, stage: &SeqStage) {
-    let entry = self.current_state_id;
+    let before = self.current_state_id;
     let exit = self.new_exit();
-    self.stage_exits.push(exit);
+
+    // =======================================================================
+    // start new stage, its states belong to its machine
+    let stage_id = self.hierarchy.stages.len() as StageID;
+    let parent = self.machines.last().and_then(|machine| machine.stage);
+    let depth = self.machines.len();
+    self.machines.push(
+      Machine {
+        stage: Some(stage_id),
+        ..Machine::default()
+      }
+    );
+
+    let entry = self.new_state();
+    let leave = self.new_state();
+    self.stage_exits.push(leave);
+    self.machine().entry = entry;
+    self.machine().exit = Some(leave);
+
+    self.hierarchy.stages.insert(
+      stage_id,
+      StageInfo {
+        name: stage.stage.to_string(),
+        player: stage.player.clone(),
+        depth,
+        parent,
+        entry,
+        exit: leave,
+      }
+    );
+
+    self.new_transition(
+      before,
+      entry,
+      Transition::EnterStage(stage_id)
+    );
+
     let end_condition = stage.end_condition.clone();
 
+    // leave the stage once the end condition holds
     self.new_transition(
       entry,
-      exit,
-      Transition::NotEndCondition(end_condition.clone())
+      leave,
+      Transition::EndCondition(end_condition.clone())
     );
 
     let else_state = self.new_state();
@@ -203,19 +419,41 @@ impl FSMBuilder {
     self.new_transition(
       entry, 
       else_state,
-      Transition::EndCondition(end_condition.clone())
+      Transition::NotEndCondition(end_condition.clone())
     );
 
+    self.turn_ends.push(Vec::new());
     self.build_flows(&stage.flows);
 
+    // `end turn` skips the rest of the body
+    let round_end = self.current_state_id;
+    for turn_end in self.turn_ends.pop().unwrap_or_default() {
+      self.new_transition(
+        turn_end,
+        round_end,
+        Transition::Action(Rule::EndTurn)
+      );
+    }
+
     self.new_transition(
-      self.current_state_id,
+      round_end,
       entry,
       Transition::StageCounter
     );
 
     self.stage_exits.pop();
 
+    self.new_transition(
+      leave,
+      exit,
+      Transition::ExitStage(stage_id)
+    );
+
+    let machine = self.machines.pop().unwrap_or_default();
+    self.machine().children.push(machine);
+    // end stage
+    // =======================================================================
+
     // continue building from the exit
     self.current_state_id = exit;
   }
@@ -225,7 +463,11 @@ impl FSMBuilder {
     match rule {
       Rule::EndStage => {
         let entry = self.current_state_id;
-        let exit = self.stage_exits.last().expect("No Stage found to end!").clone();
+        let Some(exit) = self.stage_exits.last().copied() else {
+          // keep building, to report the errors of the following flows as well
+          self.error(BuildErrorKind::EndStageOutsideStage);
+          return
+        };
 
         self.new_transition(
           entry,
@@ -233,8 +475,34 @@ impl FSMBuilder {
           Transition::Action(rule.clone())
         );
 
-        // continue building from the exit
-        self.current_state_id = exit;
+        // the stage is left through its exit, the flows after `end stage`
+        // continue from a new state that only the paths around it reach (e.g. a false IfRule)
+        self.new_state();
+      },
+
+      Rule::EndGameWithWinner(_) => {
+        let entry = self.current_state_id;
+        let goal = self.new_state();
+        self.hierarchy.goals.push(goal);
+
+        self.new_transition(
+          entry,
+          goal,
+          Transition::Action(rule.clone())
+        );
+
+        // the game is over, the flows after it are only reached around it
+        self.new_state();
+      },
+
+      Rule::EndTurn if !self.turn_ends.is_empty() => {
+        let entry = self.current_state_id;
+        if let Some(turn_ends) = self.turn_ends.last_mut() {
+          turn_ends.push(entry);
+        }
+
+        // like `end stage`, the flows after it are only reached around it
+        self.new_state();
       },
 
       _ => {
@@ -23,7 +23,9 @@ pub fn fsm_to_dot(fsm: &FSM, path: &Path) -> Result<(), Box<dyn std::error::Erro
     // mark start and end
     writeln!(file, "  start [shape=point];").unwrap();
     writeln!(file, "  start -> {};", fsm.entry).unwrap();
-    // writeln!(file, "  {} [shape=doublecircle];", fsm.end).unwrap();
+    for goal in &fsm.goals {
+        writeln!(file, "  {} [shape=doublecircle];", goal).unwrap();
+    }
 
     writeln!(file, "}}").unwrap();
 
@@ -0,0 +1,163 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::fsm::*;
+
+/// One level of a HierarchicalFSM: the game outside of all stages, or the body of one SeqStage.
+/// A stage nested in the level is not part of its states, it is a child machine.
+///
+/// Machines are only connected by EnterStage, from a state of the parent to the entry of the child,
+/// and by ExitStage, from the exit of the child to a state of the parent.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Machine {
+  /// The stage of the machine, None for the top level.
+  pub stage: Option<StageID>,
+  pub entry: StateID,
+  /// The state the ExitStage transition starts at, None for the top level.
+  pub exit: Option<StateID>,
+  /// The states of the level with all of their outgoing transitions,
+  /// including the ones into the children and out of the machine.
+  pub states: HashMap<StateID, Vec<(TransitionID, StateID)>>,
+  pub transitions: HashMap<TransitionID, Transition>,
+  /// The stages directly nested in the level, in the order of the game.
+  pub children: Vec<Machine>,
+}
+
+/// The FSM of a game as one machine per SeqStage, nested like the stages of the game.
+/// The FSMBuilder lowers every stage into its own machine, `flatten` composes them into the FSM.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct HierarchicalFSM {
+  pub root: Machine,
+  pub goals: Vec<StateID>,
+  pub stages: HashMap<StageID, StageInfo>,
+}
+
+impl Machine {
+  /// Adds a state to the machine.
+  pub(crate) fn add_state(&mut self, state_id: StateID) {
+    self.states.insert(state_id, Vec::new());
+  }
+
+  /// Adds a transition that starts at a state of the machine.
+  pub(crate) fn add_transition(
+    &mut self,
+    from_state: StateID,
+    to_state: StateID,
+    transition_id: TransitionID,
+    transition: Transition
+  ) -> Result<(), BuildErrorKind> {
+    let transitions = self.states
+      .get_mut(&from_state)
+      .ok_or(BuildErrorKind::UnknownState(from_state))?;
+    transitions.push((transition_id, to_state));
+
+    self.transitions.insert(transition_id, transition);
+
+    Ok(())
+  }
+
+  /// The machine and all nested machines.
+  pub fn machines(&self) -> Vec<&Machine> {
+    let mut machines = vec![self];
+    for child in self.children.iter() {
+      machines.extend(child.machines());
+    }
+
+    machines
+  }
+
+  /// Removes the states that are not kept, with the transitions that start at them.
+  pub(crate) fn retain_states(&mut self, keep: &HashSet<StateID>) {
+    let removed = self.states
+      .keys()
+      .filter(|state| !keep.contains(state))
+      .copied()
+      .collect::<Vec<_>>();
+    for state in removed {
+      for (transition_id, _) in self.states.remove(&state).unwrap_or_default() {
+        self.transitions.remove(&transition_id);
+      }
+    }
+
+    for child in self.children.iter_mut() {
+      child.retain_states(keep);
+    }
+  }
+
+  /// The machine of the stage, searched among the nested machines.
+  pub fn find(&self, stage: StageID) -> Option<&Machine> {
+    if self.stage == Some(stage) {
+      return Some(self)
+    }
+
+    self.children
+      .iter()
+      .find_map(|child| child.find(stage))
+  }
+
+  /// Adds the states and transitions of the machine and all nested machines to the FSM.
+  fn flatten_into(&self, fsm: &mut FSM) {
+    for (state, edges) in self.states.iter() {
+      fsm.states.insert(*state, edges.clone());
+      if let Some(stage) = self.stage {
+        fsm.state_stages.insert(*state, stage);
+      }
+    }
+
+    for (transition_id, transition) in self.transitions.iter() {
+      fsm.transitions.insert(*transition_id, transition.clone());
+      if let Some(stage) = self.stage {
+        fsm.transition_stages.insert(*transition_id, stage);
+      }
+    }
+
+    for child in self.children.iter() {
+      child.flatten_into(fsm);
+    }
+  }
+}
+
+impl HierarchicalFSM {
+  /// Composes the machines into the flat FSM the game is played on.
+  pub fn flatten(&self) -> FSM {
+    let mut fsm = FSM {
+      entry: self.root.entry,
+      goals: self.goals.clone(),
+      stages: self.stages.clone(),
+      ..FSM::default()
+    };
+    self.root.flatten_into(&mut fsm);
+
+    fsm
+  }
+
+  /// The machine of the stage.
+  pub fn machine(&self, stage: StageID) -> Option<&Machine> {
+    self.root.find(stage)
+  }
+
+  /// The level of the stage alone as a flat FSM, starting at the entry of the stage.
+  /// Nested stages are left out, their EnterStage transitions lead to states that are not part of it.
+  pub fn zoom(&self, stage: StageID) -> Option<FSM> {
+    let machine = self.machine(stage)?;
+
+    Some(
+      FSM {
+        entry: machine.entry,
+        goals: self.goals
+          .iter()
+          .filter(|goal| machine.states.contains_key(goal))
+          .copied()
+          .collect(),
+        stages: self.stages
+          .iter()
+          .filter(|(id, info)| **id == stage || info.parent == Some(stage))
+          .map(|(id, info)| (*id, info.clone()))
+          .collect(),
+        state_stages: machine.states.keys().map(|state| (*state, stage)).collect(),
+        transition_stages: machine.transitions.keys().map(|transition| (*transition, stage)).collect(),
+        states: machine.states.clone(),
+        transitions: machine.transitions.clone(),
+      }
+    )
+  }
+}
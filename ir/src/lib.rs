@@ -1,5 +1,6 @@
 pub mod fsm;
 pub mod fsm_to_dot;
+pub mod hierarchy;
 
 pub fn add(left: u64, right: u64) -> u64 {
     left + right
@@ -7,6 +7,7 @@ mod test {
 
   use ir::fsm::*;
   use ir::fsm_to_dot::*;
+  use ir::hierarchy::*;
 
   fn show_graph(fsm: &FSM, name: &str) {
     let dot_path_name: &str = &format!("target/fsm/{}.dot", name);
@@ -41,7 +42,7 @@ mod test {
           FlowComponent::Rule(Rule::EndTurn)
         ] 
       }
-    );
+    ).unwrap();
 
     show_graph(&fsm, "rule");    
   }
@@ -67,7 +68,7 @@ mod test {
           )
         ] 
       }
-    );
+    ).unwrap();
 
     show_graph(&fsm, "if_rule");
   }
@@ -90,7 +91,7 @@ mod test {
           )
         ] 
       }
-    );
+    ).unwrap();
 
     show_graph(&fsm, "optional_rule");
   }
@@ -122,7 +123,7 @@ mod test {
           )
         ] 
       }
-    );
+    ).unwrap();
 
     show_graph(&fsm, "choice_rule");
   }
@@ -155,10 +156,47 @@ mod test {
           )
         ] 
       }
-    );
+    ).unwrap();
 
     show_graph(&fsm, "stage");
   }
+
+  #[test]
+  fn test_stage_end_condition() {
+    let mut builder = FSMBuilder::default();
+
+    let fsm = builder.build_fsm(
+      Game {
+        flows: vec![
+          FlowComponent::Stage(
+            SeqStage {
+              stage: format_ident!("Play"),
+              player: PlayerExpr::Current,
+              end_condition: EndCondition::UntilRep(Repititions { times: IntExpr::Int(2) }),
+              flows: vec![
+                FlowComponent::Rule(Rule::CycleAction(PlayerExpr::Next)),
+              ]
+            }
+          )
+        ]
+      }
+    ).unwrap();
+
+    let info = fsm.stages.values().next().unwrap();
+    let edge = |filter: fn(&Transition) -> bool| {
+      fsm.states[&info.entry]
+        .iter()
+        .find(|(id, _)| filter(&fsm.transitions[id]))
+        .map(|(_, to)| *to)
+        .unwrap()
+    };
+
+    // the stage is left once its end condition holds, the body is played while it does not
+    let leave = edge(|transition| matches!(transition, Transition::EndCondition(_)));
+    let body = edge(|transition| matches!(transition, Transition::NotEndCondition(_)));
+    assert_eq!(leave, info.exit);
+    assert!(fsm.states[&body].iter().any(|(id, _)| matches!(fsm.transitions[id], Transition::Action(Rule::CycleAction(_)))));
+  }
   
   #[test]
   fn test_game() {
@@ -616,9 +654,404 @@ mod test {
           ),
         ]
       }
-    );
+    ).unwrap();
 
     show_graph(&fsm, "game");
   }
-  
-}
\ No newline at end of file
+
+  #[test]
+  fn test_build_errors() {
+    let mut builder = FSMBuilder::default();
+
+    let errors = builder.build_fsm(
+      Game {
+        flows: vec![
+          FlowComponent::Rule(Rule::EndStage),
+          FlowComponent::Stage(
+            SeqStage {
+              stage: format_ident!("Play"),
+              player: PlayerExpr::Current,
+              end_condition: EndCondition::UntilEnd,
+              flows: vec![
+                FlowComponent::ChoiceRule(
+                  ChoiceRule {
+                    options: vec![
+                      FlowComponent::Rule(Rule::EndStage),
+                      FlowComponent::Rule(Rule::EndTurn),
+                    ]
+                  }
+                ),
+              ]
+            }
+          ),
+          FlowComponent::ChoiceRule(
+            ChoiceRule {
+              options: vec![
+                FlowComponent::Rule(Rule::EndTurn),
+                FlowComponent::IfRule(
+                  IfRule {
+                    condition: BoolExpr::OutOfStagePlayer(PlayerExpr::Current),
+                    flows: vec![
+                      FlowComponent::Rule(Rule::EndTurn),
+                      FlowComponent::Rule(Rule::EndStage),
+                    ]
+                  }
+                ),
+              ]
+            }
+          ),
+        ]
+      }
+    ).unwrap_err();
+
+    // every misplaced end stage is reported, the one inside of the stage is not
+    assert_eq!(
+      errors,
+      vec![
+        BuildError { kind: BuildErrorKind::EndStageOutsideStage, path: vec![0] },
+        BuildError { kind: BuildErrorKind::EndStageOutsideStage, path: vec![2, 1, 1] },
+      ]
+    );
+    assert_eq!(errors[1].to_string(), "flow 2.1.1: end stage outside of a stage");
+  }
+
+  #[test]
+  fn test_stage_annotations() {
+    let mut builder = FSMBuilder::default();
+
+    let fsm = builder.build_fsm(
+      Game {
+        flows: vec![
+          FlowComponent::Stage(
+            SeqStage {
+              stage: format_ident!("Round"),
+              player: PlayerExpr::Current,
+              end_condition: EndCondition::UntilEnd,
+              flows: vec![
+                FlowComponent::Stage(
+                  SeqStage {
+                    stage: format_ident!("Trick"),
+                    player: PlayerExpr::Next,
+                    end_condition: EndCondition::UntilRep(Repititions { times: IntExpr::Int(4) }),
+                    flows: vec![
+                      FlowComponent::Rule(Rule::EndStage),
+                    ]
+                  }
+                ),
+                FlowComponent::Rule(Rule::EndTurn),
+              ]
+            }
+          ),
+        ]
+      }
+    ).unwrap();
+
+    assert_eq!(fsm.stages.len(), 2);
+    let (round, info) = fsm.stages.iter().find(|(_, info)| info.name == "Round").unwrap();
+    assert_eq!((info.depth, info.parent, &info.player), (0, None, &PlayerExpr::Current));
+    let (trick, info) = fsm.stages.iter().find(|(_, info)| info.name == "Trick").unwrap();
+    assert_eq!((info.depth, info.parent, &info.player), (1, Some(*round), &PlayerExpr::Next));
+
+    // the entry of the game is outside of both stages, the entry of a stage is inside of it
+    assert_eq!(fsm.stage_of_state(fsm.entry), None);
+    assert_eq!(fsm.stage_of_state(info.entry).unwrap().name, "Trick");
+
+    // every stage is entered and left by exactly one transition, inside of the parent stage
+    for (stage, parent) in [(*round, None), (*trick, Some("Round"))] {
+      let enter = fsm.transitions
+        .iter()
+        .filter(|(_, transition)| matches!(transition, Transition::EnterStage(id) if *id == stage))
+        .map(|(id, _)| *id)
+        .collect::<Vec<_>>();
+      let exit = fsm.transitions
+        .iter()
+        .filter(|(_, transition)| matches!(transition, Transition::ExitStage(id) if *id == stage))
+        .map(|(id, _)| *id)
+        .collect::<Vec<_>>();
+      assert_eq!((enter.len(), exit.len()), (1, 1));
+
+      let enclosing = fsm.stage_of_transition(enter[0]).map(|info| info.name.as_str());
+      assert_eq!(enclosing, parent);
+      assert_eq!(fsm.transition_stages[&exit[0]], stage);
+    }
+
+    // `end stage` jumps to the state of the ExitStage transition
+    let info = &fsm.stages[trick];
+    let end_stage = fsm.transitions
+      .iter()
+      .find(|(_, transition)| matches!(transition, Transition::Action(Rule::EndStage)))
+      .map(|(id, _)| *id)
+      .unwrap();
+    assert!(fsm.states.values().flatten().any(|edge| *edge == (end_stage, info.exit)));
+  }
+
+  #[test]
+  fn test_end_turn() {
+    let mut builder = FSMBuilder::default();
+
+    let fsm = builder.build_fsm(
+      Game {
+        flows: vec![
+          FlowComponent::Stage(
+            SeqStage {
+              stage: format_ident!("Play"),
+              player: PlayerExpr::Current,
+              end_condition: EndCondition::UntilEnd,
+              flows: vec![
+                FlowComponent::IfRule(
+                  IfRule {
+                    condition: BoolExpr::OutOfStagePlayer(PlayerExpr::Current),
+                    flows: vec![
+                      FlowComponent::Rule(Rule::EndTurn),
+                    ]
+                  }
+                ),
+                FlowComponent::Rule(Rule::CycleAction(PlayerExpr::Next)),
+              ]
+            }
+          ),
+          FlowComponent::Rule(Rule::EndTurn),
+        ]
+      }
+    ).unwrap();
+
+    let edges = |filter: fn(&Transition) -> bool| {
+      fsm.states
+        .iter()
+        .flat_map(|(from, edges)| edges.iter().map(move |(id, to)| (*from, *id, *to)))
+        .filter(|(_, id, _)| filter(&fsm.transitions[id]))
+        .collect::<Vec<_>>()
+    };
+
+    // inside of the stage `end turn` jumps to the state that starts the next round
+    let end_turns = edges(|transition| matches!(transition, Transition::Action(Rule::EndTurn)));
+    let (round_end, _, _) = edges(|transition| matches!(transition, Transition::StageCounter))[0];
+    assert_eq!(end_turns.len(), 2);
+    let inside = end_turns.iter().filter(|(_, id, _)| fsm.transition_stages.contains_key(id)).collect::<Vec<_>>();
+    assert_eq!(inside.len(), 1);
+    assert_eq!(inside[0].2, round_end);
+
+    // the cycle is skipped by the turn end
+    let (cycle_from, _, cycle_to) = edges(|transition| matches!(transition, Transition::Action(Rule::CycleAction(_))))[0];
+    assert_eq!(cycle_to, round_end);
+    assert_ne!(cycle_from, inside[0].0);
+  }
+
+  #[test]
+  fn test_hierarchical() {
+    let game = Game {
+      flows: vec![
+        FlowComponent::Rule(Rule::CreatePlayer(vec![format_ident!("P1"), format_ident!("P2")])),
+        FlowComponent::Stage(
+          SeqStage {
+            stage: format_ident!("Round"),
+            player: PlayerExpr::Current,
+            end_condition: EndCondition::UntilEnd,
+            flows: vec![
+              FlowComponent::Stage(
+                SeqStage {
+                  stage: format_ident!("Trick"),
+                  player: PlayerExpr::Next,
+                  end_condition: EndCondition::UntilRep(Repititions { times: IntExpr::Int(4) }),
+                  flows: vec![
+                    FlowComponent::Rule(Rule::CycleAction(PlayerExpr::Next)),
+                  ]
+                }
+              ),
+              FlowComponent::Rule(Rule::EndTurn),
+            ]
+          }
+        ),
+        FlowComponent::Stage(
+          SeqStage {
+            stage: format_ident!("Scoring"),
+            player: PlayerExpr::Current,
+            end_condition: EndCondition::UntilRep(Repititions { times: IntExpr::Int(1) }),
+            flows: vec![
+              FlowComponent::Rule(Rule::EndStage),
+            ]
+          }
+        ),
+      ]
+    };
+    let fsm = FSMBuilder::default().build_fsm(game.clone()).unwrap();
+    let hierarchical = FSMBuilder::default().build_hierarchical(game).unwrap();
+
+    // the FSM of the builder is the flattened hierarchy
+    assert_eq!(hierarchical.flatten(), fsm);
+
+    let stage = |name: &str| *fsm.stages.iter().find(|(_, info)| info.name == name).unwrap().0;
+    let (round, trick, scoring) = (stage("Round"), stage("Trick"), stage("Scoring"));
+
+    // the machines are nested like the stages, in the order of the game
+    let children = |machine: &Machine| machine.children.iter().map(|child| child.stage).collect::<Vec<_>>();
+    assert_eq!(hierarchical.root.stage, None);
+    assert_eq!(hierarchical.root.entry, fsm.entry);
+    assert_eq!(children(&hierarchical.root), vec![Some(round), Some(scoring)]);
+    assert_eq!(children(hierarchical.machine(round).unwrap()), vec![Some(trick)]);
+    assert_eq!(hierarchical.machine(scoring).unwrap().children.len(), 0);
+
+    // every state belongs to exactly one machine, with its own entry and exit
+    let mut states = 0;
+    for (stage, info) in fsm.stages.iter() {
+      let machine = hierarchical.machine(*stage).unwrap();
+      assert_eq!((machine.entry, machine.exit), (info.entry, Some(info.exit)));
+      assert!(machine.states.keys().all(|state| fsm.state_stages[state] == *stage));
+      states += machine.states.len();
+    }
+    states += hierarchical.root.states.len();
+    assert_eq!(states, fsm.states.len());
+
+    // a machine is only left by the ExitStage of its stage and only enters its children
+    let trick_machine = hierarchical.machine(trick).unwrap();
+    for (_, edges) in trick_machine.states.iter() {
+      for (transition_id, to) in edges.iter() {
+        if !trick_machine.states.contains_key(to) {
+          assert_eq!(trick_machine.transitions[transition_id], Transition::ExitStage(trick));
+        }
+      }
+    }
+
+    // zooming in keeps the level of the stage only
+    let zoomed = hierarchical.zoom(round).unwrap();
+    assert_eq!(zoomed.entry, fsm.stages[&round].entry);
+    assert_eq!(zoomed.states, hierarchical.machine(round).unwrap().states);
+    assert!(zoomed.transitions.values().any(|transition| *transition == Transition::EnterStage(trick)));
+    assert!(!zoomed.transitions.values().any(|transition| matches!(transition, Transition::Action(Rule::CycleAction(_)))));
+    assert_eq!(hierarchical.zoom(-1), None);
+
+    // errors are reported like by build_fsm
+    let mut builder = FSMBuilder::default();
+    let built = builder.build_hierarchical(Game { flows: vec![FlowComponent::Rule(Rule::EndStage)] });
+    assert_eq!(built.unwrap_err()[0].kind, BuildErrorKind::EndStageOutsideStage);
+  }
+
+  #[test]
+  fn test_goals() {
+    let mut builder = FSMBuilder::default();
+
+    let fsm = builder.build_fsm(
+      Game {
+        flows: vec![
+          FlowComponent::ChoiceRule(
+            ChoiceRule {
+              options: vec![
+                FlowComponent::Rule(Rule::EndGameWithWinner(PlayerExpr::Current)),
+                FlowComponent::Stage(
+                  SeqStage {
+                    stage: format_ident!("Play"),
+                    player: PlayerExpr::Current,
+                    end_condition: EndCondition::UntilEnd,
+                    flows: vec![
+                      FlowComponent::Rule(Rule::EndStage),
+                    ]
+                  }
+                ),
+              ]
+            }
+          ),
+          FlowComponent::Rule(Rule::CycleAction(PlayerExpr::Next)),
+        ]
+      }
+    ).unwrap();
+
+    // the end of the game and the end of the flows
+    let end_game = fsm.states
+      .values()
+      .flatten()
+      .find(|(id, _)| matches!(fsm.transitions[id], Transition::Action(Rule::EndGameWithWinner(_))))
+      .map(|(_, to)| *to)
+      .unwrap();
+    let end_flows = fsm.states
+      .values()
+      .flatten()
+      .find(|(id, _)| matches!(fsm.transitions[id], Transition::Action(Rule::CycleAction(_))))
+      .map(|(_, to)| *to)
+      .unwrap();
+    assert_eq!(fsm.goals, vec![end_game, end_flows]);
+
+    // every state continues or is a goal, and every transition leads to a state
+    for (state, edges) in fsm.states.iter() {
+      assert!(!edges.is_empty() || fsm.is_goal(*state));
+      assert!(edges.iter().all(|(_, to)| fsm.states.contains_key(to)));
+    }
+
+    // nothing follows the end of the game, the state after it is removed
+    let mut builder = FSMBuilder::default();
+    let fsm = builder.build_fsm(
+      Game {
+        flows: vec![
+          FlowComponent::Rule(Rule::EndGameWithWinner(PlayerExpr::Current)),
+        ]
+      }
+    ).unwrap();
+    assert_eq!(fsm.states.len(), 2);
+    assert_eq!(fsm.goals.len(), 1);
+    assert_eq!(fsm.states[&fsm.entry], vec![(1, fsm.goals[0])]);
+
+    // a game without flows ends where it starts
+    let fsm = FSMBuilder::default().build_fsm(Game { flows: vec![] }).unwrap();
+    assert_eq!(fsm.goals, vec![fsm.entry]);
+
+    // an option that only ends the game if its condition holds gets stuck otherwise
+    let errors = FSMBuilder::default().build_fsm(
+      Game {
+        flows: vec![
+          FlowComponent::ChoiceRule(
+            ChoiceRule {
+              options: vec![
+                FlowComponent::IfRule(
+                  IfRule {
+                    condition: BoolExpr::OutOfStagePlayer(PlayerExpr::Current),
+                    flows: vec![
+                      FlowComponent::Rule(Rule::EndGameWithWinner(PlayerExpr::Current)),
+                    ]
+                  }
+                ),
+                FlowComponent::Rule(Rule::CycleAction(PlayerExpr::Next)),
+              ]
+            }
+          ),
+        ]
+      }
+    ).unwrap_err();
+    assert_eq!(errors.len(), 1);
+    assert!(matches!(errors[0].kind, BuildErrorKind::DeadEnd(_)));
+    assert_eq!(errors[0].path, vec![0, 0, 0]);
+    assert!(errors[0].to_string().starts_with("flow 0.0.0: the game gets stuck at state"));
+
+    // a trailing `end stage` never reaches the end of the round, it is removed with its StageCounter
+    let fsm = FSMBuilder::default().build_fsm(
+      Game {
+        flows: vec![
+          FlowComponent::Stage(
+            SeqStage {
+              stage: format_ident!("Play"),
+              player: PlayerExpr::Current,
+              end_condition: EndCondition::UntilEnd,
+              flows: vec![
+                FlowComponent::Rule(Rule::CycleAction(PlayerExpr::Next)),
+                FlowComponent::Rule(Rule::EndStage),
+              ]
+            }
+          ),
+        ]
+      }
+    ).unwrap();
+    assert!(!fsm.transitions.values().any(|transition| *transition == Transition::StageCounter));
+
+    let mut reached = vec![fsm.entry];
+    let mut queue = vec![fsm.entry];
+    while let Some(state) = queue.pop() {
+      for (_, to) in fsm.states[&state].iter() {
+        if !reached.contains(to) {
+          reached.push(*to);
+          queue.push(*to);
+        }
+      }
+    }
+    assert_eq!(reached.len(), fsm.states.len());
+    assert!(fsm.goals.iter().all(|goal| reached.contains(goal)));
+    assert_eq!(fsm.transitions.len(), fsm.states.values().flatten().count());
+  }
+}
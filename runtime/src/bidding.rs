@@ -0,0 +1,123 @@
+use ast::ast::*;
+
+use crate::error::RuntimeError;
+use crate::input::{Answer, Options};
+use crate::memory::MemoryValue;
+use crate::state::GameState;
+
+/// Bids a player may make, bids are never negative.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BidRange {
+  pub min: i32,
+  /// None if there is no upper bound.
+  pub max: Option<i32>,
+  /// A value that is excluded by `range(!= n)`.
+  pub except: Option<i32>,
+}
+
+impl BidRange {
+  pub fn contains(&self, bid: i32) -> bool {
+    bid >= self.min
+      && self.max.is_none_or(|max| bid <= max)
+      && self.except != Some(bid)
+  }
+
+  /// The only allowed bid, if there is exactly one.
+  pub fn single(&self) -> Option<i32> {
+    match self.max {
+      Some(max) if max == self.min && self.except != Some(max) => Some(max),
+      _ => None,
+    }
+  }
+
+  pub fn is_empty(&self) -> bool {
+    match self.max {
+      Some(max) => max < self.min || (max == self.min && self.except == Some(max)),
+      None => false,
+    }
+  }
+}
+
+impl GameState {
+  /// The bids the Quantity allows, evaluated against the current state (e.g. `range(> memory Bid)`).
+  pub fn bid_range(&self, quantity: &Quantity) -> Result<BidRange, RuntimeError> {
+    let (min, max, except) = match quantity {
+      Quantity::Int(int) => {
+        let int = self.eval_int(int)?;
+
+        (int, Some(int), None)
+      },
+      Quantity::Quantifier(Quantifier::Any) => (0, None, None),
+      Quantity::Quantifier(Quantifier::All) => {
+        return Err(RuntimeError::Unsupported(String::from("bid all")))
+      },
+      Quantity::IntRange(range) => match range {
+        IntRange::Eq(int) => {
+          let int = self.eval_int(int)?;
+
+          (int, Some(int), None)
+        },
+        IntRange::Neq(int) => (0, None, Some(self.eval_int(int)?)),
+        IntRange::Gt(int) => (self.eval_int(int)?.checked_add(1).ok_or(RuntimeError::Overflow)?, None, None),
+        IntRange::Ge(int) => (self.eval_int(int)?, None, None),
+        IntRange::Lt(int) => (0, Some(self.eval_int(int)?.checked_sub(1).ok_or(RuntimeError::Overflow)?), None),
+        IntRange::Le(int) => (0, Some(self.eval_int(int)?), None),
+      },
+    };
+
+    let range = BidRange { min: min.max(0), max, except };
+    if range.is_empty() {
+      return Err(RuntimeError::NoValidBid)
+    }
+
+    Ok(range)
+  }
+
+  /// The bids the current player may make, None if only one bid is allowed.
+  pub fn bid_options(&self, quantity: &Quantity) -> Result<Option<Options>, RuntimeError> {
+    let range = self.bid_range(quantity)?;
+    if range.single().is_some() {
+      return Ok(None)
+    }
+
+    Ok(Some(Options::Bid(range)))
+  }
+
+  /// Validates the bid of the current player and stores it, in the memory if one is given.
+  pub fn execute_bid(&mut self, rule: &Rule, answer: Option<&Answer>) -> Result<(), RuntimeError> {
+    let (quantity, memory) = match rule {
+      Rule::BidAction(quantity) => (quantity, None),
+      Rule::BidActionMemory(memory, quantity) => (quantity, Some(memory.to_string())),
+      _ => return Err(RuntimeError::Unsupported(format!("{:?}", rule))),
+    };
+
+    let range = self.bid_range(quantity)?;
+    let bid = match (range.single(), answer) {
+      (Some(bid), _) => bid,
+      (None, Some(Answer::Int(bid))) if range.contains(*bid) => *bid,
+      (None, Some(Answer::Int(bid))) => return Err(RuntimeError::InvalidBid(*bid)),
+      (None, None) => return Err(RuntimeError::UndecidedQuantity),
+      _ => return Err(RuntimeError::InvalidQuantity),
+    };
+
+    if let Some(memory) = memory {
+      self.set_memory(&memory, MemoryValue::Int(bid))?;
+    }
+
+    let player = self.current_player()?.to_string();
+    match self.bids.iter_mut().find(|(name, _)| *name == player) {
+      Some((_, last)) => *last = bid,
+      None => self.bids.push((player, bid)),
+    }
+
+    Ok(())
+  }
+
+  /// The last bid of the player.
+  pub fn bid_of(&self, player: &str) -> Option<i32> {
+    self.bids
+      .iter()
+      .find(|(name, _)| name == player)
+      .map(|(_, bid)| *bid)
+  }
+}
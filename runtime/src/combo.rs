@@ -0,0 +1,468 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use ast::ast::*;
+
+use crate::error::RuntimeError;
+use crate::precedence::is_run;
+use crate::state::{CardId, GameState};
+
+/// Matches sets of cards against a FilterExpr.
+///
+/// A filter always looks at a set of cards as a whole: `same Suite` holds if all cards share the suite,
+/// `size >= 3` if there are at least three cards.
+/// `not Combo` holds if none of the cards is part of the Combo somewhere in the context,
+/// the cards the subsets are taken from (e.g. a hand).
+pub struct ComboMatcher<'a> {
+  state: &'a GameState,
+  context: Vec<CardId>,
+  /// Cards of the context that are part of a combo, by the name of the combo.
+  members: RefCell<HashMap<String, Vec<CardId>>>,
+  /// Names of the combos that are currently resolved.
+  active: RefCell<Vec<String>>,
+}
+
+impl<'a> ComboMatcher<'a> {
+  pub fn new(state: &'a GameState, context: &[CardId]) -> Self {
+    ComboMatcher {
+      state,
+      context: context.to_vec(),
+      members: RefCell::new(HashMap::new()),
+      active: RefCell::new(Vec::new()),
+    }
+  }
+
+  /// Checks if the cards as a whole match the filter.
+  pub fn matches(&self, cards: &[CardId], filter: &FilterExpr) -> Result<bool, RuntimeError> {
+    match filter {
+      FilterExpr::Same(key) => {
+        let values = self.values(cards, &key.to_string())?;
+
+        Ok(values.iter().all(|value| value.is_some() && *value == values[0]))
+      },
+      FilterExpr::Distinct(key) => {
+        let values = self.values(cards, &key.to_string())?;
+        if values.iter().any(|value| value.is_none()) {
+          return Ok(false)
+        }
+
+        Ok(
+          values
+            .iter()
+            .enumerate()
+            .all(|(i, value)| !values[i + 1..].contains(value))
+        )
+      },
+      FilterExpr::Adjacent(key, precedence) => {
+        let Some(ranks) = self.ranks(cards, key, precedence)? else {
+          return Ok(false)
+        };
+        let name = precedence.to_string();
+        let len = self.state.precedence(&name)?.len_of(&key.to_string());
+
+        Ok(is_run(&ranks, len, self.state.wrap_around.contains(&name)))
+      },
+      FilterExpr::Higher(key, precedence) => {
+        let Some(ranks) = self.ranks(cards, key, precedence)? else {
+          return Ok(false)
+        };
+
+        Ok(ranks.windows(2).all(|pair| pair[0] < pair[1]))
+      },
+      FilterExpr::Lower(key, precedence) => {
+        let Some(ranks) = self.ranks(cards, key, precedence)? else {
+          return Ok(false)
+        };
+
+        Ok(ranks.windows(2).all(|pair| pair[0] > pair[1]))
+      },
+      FilterExpr::SizeEq(_)
+      | FilterExpr::SizeNeq(_)
+      | FilterExpr::SizeGt(_)
+      | FilterExpr::SizeLt(_)
+      | FilterExpr::SizeGe(_)
+      | FilterExpr::SizeLe(_) => self.size_matches(cards.len(), filter),
+      FilterExpr::KeyEq(key, string) => {
+        let string = self.state.eval_string(string)?;
+
+        Ok(
+          self.values(cards, &key.to_string())?
+            .into_iter()
+            .all(|value| value == Some(string.as_str()))
+        )
+      },
+      FilterExpr::KeyNeq(key, string) => {
+        let string = self.state.eval_string(string)?;
+
+        Ok(
+          self.values(cards, &key.to_string())?
+            .into_iter()
+            .all(|value| value != Some(string.as_str()))
+        )
+      },
+      FilterExpr::Combo(combo) => {
+        let combo = combo.to_string();
+        let filter = self.state.combo(&combo)?;
+
+        self.resolve(&combo, || self.matches(cards, filter))
+      },
+      FilterExpr::NotCombo(combo) => {
+        let members = self.combo_members(&combo.to_string())?;
+
+        Ok(cards.iter().all(|card| !members.contains(card)))
+      },
+      FilterExpr::And(left, right) => {
+        Ok(self.matches(cards, left)? && self.matches(cards, right)?)
+      },
+      FilterExpr::Or(left, right) => {
+        Ok(self.matches(cards, left)? || self.matches(cards, right)?)
+      },
+    }
+  }
+
+  /// All non-empty subsets of the context that match the filter.
+  /// The cards of a subset keep the order of the context.
+  pub fn subsets(&self, filter: &FilterExpr) -> Result<Vec<Vec<CardId>>, RuntimeError> {
+    let mut subsets = Vec::new();
+    self.extend(filter, 0, &mut Vec::new(), &mut subsets)?;
+
+    Ok(subsets)
+  }
+
+  /// Cards of the context that are part of at least one matching subset, in the order of the context.
+  /// Computed without listing the subsets if the filter allows it, see direct_members.
+  pub fn members(&self, filter: &FilterExpr) -> Result<Vec<CardId>, RuntimeError> {
+    if let Some(members) = self.direct_members(filter)? {
+      return Ok(members)
+    }
+
+    let subsets = self.subsets(filter)?;
+
+    Ok(
+      self.context
+        .iter()
+        .filter(|card| subsets.iter().any(|subset| subset.contains(card)))
+        .copied()
+        .collect()
+    )
+  }
+
+  /// Members of a conjunction of size filters, filters on single cards (`Key(..)`, `not Combo`), `same` filters
+  /// and at most one of `distinct`, `higher`, `lower` and `adjacent`. None for any other filter.
+  ///
+  /// Every subset of a matching set that keeps the card matches these filters as well, except for the size
+  /// (a shorter run around the card is still a run). So a card is a member iff the largest matching set
+  /// that contains it is at least as large as the smallest size the size filters allow.
+  fn direct_members(&self, filter: &FilterExpr) -> Result<Option<Vec<CardId>>, RuntimeError> {
+    let mut conjuncts = Vec::new();
+    let mut pending = vec![filter];
+    while let Some(filter) = pending.pop() {
+      match filter {
+        FilterExpr::And(left, right) => {
+          pending.push(right);
+          pending.push(left);
+        },
+        filter => conjuncts.push(filter),
+      }
+    }
+
+    let mut sizes = Vec::new();
+    // filters every card of a set has to match on its own
+    let mut singles = Vec::new();
+    let mut same = Vec::new();
+    let mut special = None;
+    for conjunct in conjuncts.into_iter() {
+      match conjunct {
+        FilterExpr::SizeEq(_)
+        | FilterExpr::SizeNeq(_)
+        | FilterExpr::SizeGt(_)
+        | FilterExpr::SizeLt(_)
+        | FilterExpr::SizeGe(_)
+        | FilterExpr::SizeLe(_) => sizes.push(conjunct),
+        FilterExpr::KeyEq(_, _) | FilterExpr::KeyNeq(_, _) | FilterExpr::NotCombo(_) => singles.push(conjunct),
+        FilterExpr::Same(key) => {
+          same.push(key.to_string());
+          singles.push(conjunct);
+        },
+        FilterExpr::Distinct(_)
+        | FilterExpr::Higher(_, _)
+        | FilterExpr::Lower(_, _)
+        | FilterExpr::Adjacent(_, _) if special.is_none() => {
+          special = Some(conjunct);
+          singles.push(conjunct);
+        },
+        _ => return Ok(None),
+      }
+    }
+
+    // the smallest size the size filters allow, larger sets can be shrunk down to it
+    let mut smallest = None;
+    for size in 1..=self.context.len() {
+      let mut allowed = true;
+      for filter in sizes.iter() {
+        allowed = allowed && self.size_matches(size, filter)?;
+      }
+      if allowed {
+        smallest = Some(size);
+        break
+      }
+    }
+    let Some(smallest) = smallest else {
+      return Ok(Some(Vec::new()))
+    };
+
+    // cards that match every filter on their own
+    let mut candidates = Vec::new();
+    for card in self.context.iter() {
+      let mut matching = true;
+      for filter in singles.iter() {
+        matching = matching && self.matches(&[*card], filter)?;
+      }
+      if matching {
+        candidates.push(*card);
+      }
+    }
+
+    let mut members = Vec::new();
+    for card in candidates.iter() {
+      // the cards a matching set with the card can consist of
+      let mut pool = Vec::new();
+      for other in candidates.iter() {
+        let mut shared = true;
+        for key in same.iter() {
+          shared = shared && self.state.card(*other)?.value(key) == self.state.card(*card)?.value(key);
+        }
+        if shared {
+          pool.push(*other);
+        }
+      }
+
+      if self.largest(*card, &pool, special)? >= smallest {
+        members.push(*card);
+      }
+    }
+
+    Ok(Some(members))
+  }
+
+  /// Size of the largest set of the pool that contains the card and matches the special filter.
+  fn largest(&self, card: CardId, pool: &[CardId], special: Option<&FilterExpr>) -> Result<usize, RuntimeError> {
+    match special {
+      Some(FilterExpr::Distinct(key)) => {
+        let mut values = self.values(pool, &key.to_string())?;
+        values.sort();
+        values.dedup();
+
+        Ok(values.len())
+      },
+      Some(FilterExpr::Higher(key, precedence)) | Some(FilterExpr::Lower(key, precedence)) => {
+        let ranks = self.ranks(pool, key, precedence)?.unwrap_or_default();
+        let index = pool.iter().position(|other| *other == card).unwrap_or_default();
+        let before = |i: usize, j: usize| match special {
+          Some(FilterExpr::Higher(_, _)) => ranks[i] < ranks[j],
+          _ => ranks[i] > ranks[j],
+        };
+
+        // longest sequence in the order of the pool that ends at, and that starts at every card
+        let mut ending = vec![1; ranks.len()];
+        for j in 0..ranks.len() {
+          for i in 0..j {
+            if before(i, j) {
+              ending[j] = ending[j].max(ending[i] + 1);
+            }
+          }
+        }
+        let mut starting = vec![1; ranks.len()];
+        for i in (0..ranks.len()).rev() {
+          for j in i + 1..ranks.len() {
+            if before(i, j) {
+              starting[i] = starting[i].max(starting[j] + 1);
+            }
+          }
+        }
+
+        Ok(ending[index] + starting[index] - 1)
+      },
+      Some(FilterExpr::Adjacent(key, precedence)) => {
+        // the run of ranks of the pool around the rank of the card, one card per rank
+        let name = precedence.to_string();
+        let len = self.state.precedence(&name)?.len_of(&key.to_string());
+        let wrap_around = self.state.wrap_around.contains(&name);
+        let ranks = self.ranks(pool, key, precedence)?.unwrap_or_default();
+        let Some(rank) = self.ranks(&[card], key, precedence)?.and_then(|ranks| ranks.first().copied()) else {
+          return Ok(0)
+        };
+
+        let mut run = 1;
+        let mut lower = rank;
+        while run < len && (lower > 0 || wrap_around) {
+          lower = (lower + len - 1) % len;
+          if !ranks.contains(&lower) {
+            break
+          }
+          run += 1;
+        }
+        let mut higher = rank;
+        while run < len && (higher + 1 < len || wrap_around) {
+          higher = (higher + 1) % len;
+          if !ranks.contains(&higher) {
+            break
+          }
+          run += 1;
+        }
+
+        Ok(run)
+      },
+      _ => Ok(pool.len()),
+    }
+  }
+
+  /// Checks if a set of the size matches the size filter.
+  fn size_matches(&self, size: usize, filter: &FilterExpr) -> Result<bool, RuntimeError> {
+    let size = size as i64;
+
+    match filter {
+      FilterExpr::SizeEq(int) => Ok(size == self.int(int)?),
+      FilterExpr::SizeNeq(int) => Ok(size != self.int(int)?),
+      FilterExpr::SizeGt(int) => Ok(size > self.int(int)?),
+      FilterExpr::SizeLt(int) => Ok(size < self.int(int)?),
+      FilterExpr::SizeGe(int) => Ok(size >= self.int(int)?),
+      FilterExpr::SizeLe(int) => Ok(size <= self.int(int)?),
+      _ => Ok(true),
+    }
+  }
+
+  /// Depth first enumeration of the subsets, pruned by blocks_extension.
+  fn extend(
+    &self,
+    filter: &FilterExpr,
+    start: usize,
+    current: &mut Vec<CardId>,
+    subsets: &mut Vec<Vec<CardId>>
+  ) -> Result<(), RuntimeError> {
+    for index in start..self.context.len() {
+      current.push(self.context[index]);
+
+      if self.matches(current, filter)? {
+        subsets.push(current.clone());
+      }
+      if !self.blocks_extension(current, filter)? {
+        self.extend(filter, index + 1, current, subsets)?;
+      }
+
+      current.pop();
+    }
+
+    Ok(())
+  }
+
+  /// Checks if no set that contains the cards can match the filter.
+  fn blocks_extension(&self, cards: &[CardId], filter: &FilterExpr) -> Result<bool, RuntimeError> {
+    match filter {
+      FilterExpr::And(left, right) => {
+        Ok(self.blocks_extension(cards, left)? || self.blocks_extension(cards, right)?)
+      },
+      FilterExpr::Or(left, right) => {
+        Ok(self.blocks_extension(cards, left)? && self.blocks_extension(cards, right)?)
+      },
+      FilterExpr::SizeEq(int) | FilterExpr::SizeLe(int) => {
+        Ok(cards.len() as i64 >= self.int(int)?)
+      },
+      FilterExpr::SizeLt(int) => {
+        Ok(cards.len() as i64 + 1 >= self.int(int)?)
+      },
+      FilterExpr::Combo(combo) => {
+        let combo = combo.to_string();
+        let filter = self.state.combo(&combo)?;
+
+        self.resolve(&combo, || self.blocks_extension(cards, filter))
+      },
+      // once violated these can not hold for more cards
+      FilterExpr::Same(_)
+      | FilterExpr::Distinct(_)
+      | FilterExpr::Higher(_, _)
+      | FilterExpr::Lower(_, _)
+      | FilterExpr::KeyEq(_, _)
+      | FilterExpr::KeyNeq(_, _)
+      | FilterExpr::NotCombo(_) => {
+        Ok(!self.matches(cards, filter)?)
+      },
+      FilterExpr::Adjacent(_, _)
+      | FilterExpr::SizeNeq(_)
+      | FilterExpr::SizeGt(_)
+      | FilterExpr::SizeGe(_) => Ok(false),
+    }
+  }
+
+  /// Cards of the context that are part of the combo.
+  fn combo_members(&self, combo: &str) -> Result<Vec<CardId>, RuntimeError> {
+    if let Some(members) = self.members.borrow().get(combo) {
+      return Ok(members.clone())
+    }
+
+    let filter = self.state.combo(combo)?;
+    let members = self.resolve(combo, || self.members(filter))?;
+    self.members.borrow_mut().insert(combo.to_string(), members.clone());
+
+    Ok(members)
+  }
+
+  /// Runs f while the combo is marked as active, fails if the combo is already active.
+  fn resolve<T>(&self, combo: &str, f: impl FnOnce() -> Result<T, RuntimeError>) -> Result<T, RuntimeError> {
+    if self.active.borrow().iter().any(|active| active == combo) {
+      return Err(RuntimeError::RecursiveCombo(combo.to_string()))
+    }
+
+    self.active.borrow_mut().push(combo.to_string());
+    let result = f();
+    self.active.borrow_mut().pop();
+
+    result
+  }
+
+  fn values(&self, cards: &[CardId], key: &str) -> Result<Vec<Option<&'a str>>, RuntimeError> {
+    let state = self.state;
+
+    cards
+      .iter()
+      .map(|card| Ok(state.card(*card)?.value(key)))
+      .collect()
+  }
+
+  /// Ranks of the cards in the precedence, None if a card is not part of the precedence.
+  fn ranks(&self, cards: &[CardId], key: &Key, precedence: &Precedence) -> Result<Option<Vec<usize>>, RuntimeError> {
+    let key = key.to_string();
+    let precedence = self.state.precedence(&precedence.to_string())?;
+
+    let mut ranks = Vec::new();
+    for card in cards.iter() {
+      match precedence.rank_of(self.state.card(*card)?, &key) {
+        Some(rank) => ranks.push(rank),
+        None => return Ok(None),
+      }
+    }
+
+    Ok(Some(ranks))
+  }
+
+  fn int(&self, int: &IntExpr) -> Result<i64, RuntimeError> {
+    Ok(self.state.eval_int(int)? as i64)
+  }
+}
+
+impl GameState {
+  /// Checks if the cards as a whole match the filter.
+  pub fn matches(&self, cards: &[CardId], filter: &FilterExpr) -> Result<bool, RuntimeError> {
+    ComboMatcher::new(self, cards).matches(cards, filter)
+  }
+
+  /// All non-empty subsets of the cards that match the filter.
+  pub fn combo_subsets(&self, cards: &[CardId], filter: &FilterExpr) -> Result<Vec<Vec<CardId>>, RuntimeError> {
+    ComboMatcher::new(self, cards).subsets(filter)
+  }
+
+  /// Cards that are part of at least one subset that matches the filter.
+  pub fn combo_members(&self, cards: &[CardId], filter: &FilterExpr) -> Result<Vec<CardId>, RuntimeError> {
+    ComboMatcher::new(self, cards).members(filter)
+  }
+}
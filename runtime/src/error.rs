@@ -0,0 +1,165 @@
+use std::fmt;
+
+use ir::fsm::{BuildError, StateID, TransitionID};
+
+use crate::memory::MemoryType;
+use crate::watchdog::Stall;
+
+/// Everything that can go wrong while running a game.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RuntimeError {
+  /// The FSM of the game could not be built.
+  InvalidGame(Vec<BuildError>),
+  /// The FSM references a state that does not exist.
+  UnknownState(StateID),
+  /// A transition id is not part of the FSM.
+  UnknownTransition(TransitionID),
+  /// More than one outgoing edge of a state is enabled at the same time.
+  AmbiguousTransition(StateID),
+  /// No outgoing edge of a state is enabled.
+  NoTransition(StateID),
+  /// A choice or optional rule was answered with a transition that was not offered.
+  InvalidDecision(TransitionID),
+  /// An answer was given although no input is pending.
+  NoPendingInput,
+  /// The answer is not of the requested type, or not one of the options of a demand.
+  InvalidAnswer,
+  /// A demand has to be answered by the current player.
+  UndecidedDemand,
+  /// A demand without any legal answer (e.g. a card of an empty location).
+  NoLegalAnswer,
+  /// `demanded` was read but the last demand did not ask for this kind of value.
+  NotDemanded(String),
+  UnknownPlayer(String),
+  UnknownTeam(String),
+  UnknownLocation(String),
+  UnknownCard(usize),
+  /// A location with the same name was already created for the owner.
+  DuplicateLocation(String),
+  /// A CardPosition refers to a card that does not exist (e.g. top of an empty location).
+  NoCard(String),
+  /// The card does not belong to a player.
+  NoOwner(usize),
+  /// The card has no value for the key.
+  UnknownKey(String),
+  IndexOutOfRange { index: i32, len: usize },
+  UnknownPointMap(String),
+  UnknownPrecedence(String),
+  UnknownCombo(String),
+  /// A combo refers to itself.
+  RecursiveCombo(String),
+  UnknownMemory(String),
+  /// A memory with the same name was already created for the owner.
+  DuplicateMemory(String),
+  /// The memory was created but never set.
+  UnsetMemory(String),
+  /// The value does not match the type of the memory.
+  MemoryTypeMismatch { memory: String, expected: MemoryType, found: MemoryType },
+  /// A move needs more cards than the source has.
+  NotEnoughCards { needed: usize, available: usize },
+  /// A TokenMove needs more tokens than a source has.
+  NotEnoughTokens { needed: usize, available: usize },
+  /// Tokens can only be created in positive amounts.
+  NegativeAmount(i32),
+  /// A move needs a single target location but the CardSet refers to several.
+  AmbiguousLocation(String),
+  /// The range of a bid does not allow any bid.
+  NoValidBid,
+  /// The bid is outside of the allowed range.
+  InvalidBid(i32),
+  /// The Quantity of a move is free and has to be answered by the acting player.
+  UndecidedQuantity,
+  /// The Quantity of the move allows no count or the answer does not satisfy it.
+  InvalidQuantity,
+  DivisionByZero,
+  Overflow,
+  /// Min or max of an empty collection.
+  EmptyCollection,
+  /// A rule needs a player but there is no turn order yet.
+  NoCurrentPlayer,
+  /// Every player is out of the stage or out of the game.
+  NoActivePlayer,
+  /// There is no active player outside of the team of the current player.
+  NoCompetitor,
+  /// A StageRoundCounter or stage related rule was used outside of a stage.
+  NoActiveStage,
+  /// A saved game could not be written or read.
+  InvalidSave(String),
+  /// No decision was taken since the start, or since the last undo.
+  NothingToUndo,
+  /// Nothing was undone since the last decision.
+  NothingToRedo,
+  /// Fewer decisions were taken than the game should be rolled back to.
+  UnknownDecision(usize),
+  /// The game stopped making progress, see `Interpreter::run_watched`.
+  Stalled(Box<Stall>),
+  /// The construct is valid DSL but has no runtime semantics yet.
+  Unsupported(String),
+}
+
+impl fmt::Display for RuntimeError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      RuntimeError::InvalidGame(errors) => {
+        let errors = errors
+          .iter()
+          .map(|error| error.to_string())
+          .collect::<Vec<_>>();
+
+        write!(f, "invalid game: {}", errors.join(", "))
+      },
+      RuntimeError::UnknownState(state) => write!(f, "state {} does not exist", state),
+      RuntimeError::UnknownTransition(transition) => write!(f, "transition {} does not exist", transition),
+      RuntimeError::AmbiguousTransition(state) => write!(f, "more than one transition of state {} is enabled", state),
+      RuntimeError::NoTransition(state) => write!(f, "no transition of state {} is enabled", state),
+      RuntimeError::InvalidDecision(transition) => write!(f, "transition {} is not a valid decision", transition),
+      RuntimeError::NoPendingInput => write!(f, "no input is pending"),
+      RuntimeError::InvalidAnswer => write!(f, "the answer is not one of the options"),
+      RuntimeError::UndecidedDemand => write!(f, "the demand has to be answered by the player"),
+      RuntimeError::NoLegalAnswer => write!(f, "the demand has no legal answer"),
+      RuntimeError::NotDemanded(kind) => write!(f, "no {} was demanded", kind),
+      RuntimeError::UnknownPlayer(player) => write!(f, "unknown player '{}'", player),
+      RuntimeError::UnknownTeam(team) => write!(f, "unknown team '{}'", team),
+      RuntimeError::UnknownLocation(location) => write!(f, "unknown location '{}'", location),
+      RuntimeError::UnknownCard(card) => write!(f, "card {} does not exist", card),
+      RuntimeError::DuplicateLocation(location) => write!(f, "location '{}' already exists", location),
+      RuntimeError::NoCard(position) => write!(f, "there is no card at {}", position),
+      RuntimeError::NoOwner(card) => write!(f, "card {} is not owned by a player", card),
+      RuntimeError::UnknownKey(key) => write!(f, "card has no key '{}'", key),
+      RuntimeError::IndexOutOfRange { index, len } => write!(f, "index {} is out of range for length {}", index, len),
+      RuntimeError::UnknownPointMap(point_map) => write!(f, "unknown pointmap '{}'", point_map),
+      RuntimeError::UnknownPrecedence(precedence) => write!(f, "unknown precedence '{}'", precedence),
+      RuntimeError::UnknownCombo(combo) => write!(f, "unknown combo '{}'", combo),
+      RuntimeError::RecursiveCombo(combo) => write!(f, "combo '{}' refers to itself", combo),
+      RuntimeError::UnknownMemory(memory) => write!(f, "unknown memory '{}'", memory),
+      RuntimeError::DuplicateMemory(memory) => write!(f, "memory '{}' already exists", memory),
+      RuntimeError::UnsetMemory(memory) => write!(f, "memory '{}' has no value", memory),
+      RuntimeError::MemoryTypeMismatch { memory, expected, found } => {
+        write!(f, "memory '{}' holds a {} but got a {}", memory, expected, found)
+      },
+      RuntimeError::NotEnoughCards { needed, available } => write!(f, "{} cards needed but only {} available", needed, available),
+      RuntimeError::NotEnoughTokens { needed, available } => write!(f, "{} tokens needed but only {} available", needed, available),
+      RuntimeError::NegativeAmount(amount) => write!(f, "can not create {} tokens", amount),
+      RuntimeError::AmbiguousLocation(cardset) => write!(f, "{} is not a single location", cardset),
+      RuntimeError::NoValidBid => write!(f, "no bid is allowed"),
+      RuntimeError::InvalidBid(bid) => write!(f, "bid {} is not allowed", bid),
+      RuntimeError::UndecidedQuantity => write!(f, "the quantity has to be chosen by the player"),
+      RuntimeError::InvalidQuantity => write!(f, "the quantity allows no count or the answer does not satisfy it"),
+      RuntimeError::DivisionByZero => write!(f, "division by zero"),
+      RuntimeError::Overflow => write!(f, "integer overflow"),
+      RuntimeError::EmptyCollection => write!(f, "min or max of an empty collection"),
+      RuntimeError::NoCurrentPlayer => write!(f, "no current player, is there a turnorder?"),
+      RuntimeError::NoActivePlayer => write!(f, "every player is out"),
+      RuntimeError::NoCompetitor => write!(f, "no competitor for the current player"),
+      RuntimeError::NoActiveStage => write!(f, "not inside of a stage"),
+      RuntimeError::InvalidSave(reason) => write!(f, "invalid save: {}", reason),
+      RuntimeError::NothingToUndo => write!(f, "there is no decision to undo"),
+      RuntimeError::NothingToRedo => write!(f, "there is no decision to redo"),
+      RuntimeError::UnknownDecision(decision) => write!(f, "decision {} was not taken", decision),
+      RuntimeError::Stalled(stall) => write!(f, "the game stalled: {}", stall),
+      RuntimeError::Unsupported(what) => write!(f, "not supported at runtime: {}", what),
+    }
+  }
+}
+
+impl std::error::Error for RuntimeError {}
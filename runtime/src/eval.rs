@@ -0,0 +1,539 @@
+use std::cmp::Ordering;
+
+use ast::ast::*;
+
+use crate::error::RuntimeError;
+use crate::input::Demanded;
+use crate::state::{CardId, GameState, Owner};
+
+impl GameState {
+  /// Resolves a PlayerExpr to the name of a player.
+  pub fn eval_player(&self, player: &PlayerExpr) -> Result<String, RuntimeError> {
+    match player {
+      PlayerExpr::PlayerName(name) => {
+        let name = name.to_string();
+        self.player(&name)?;
+
+        Ok(name)
+      },
+      PlayerExpr::Current => {
+        Ok(self.current_player()?.to_string())
+      },
+      PlayerExpr::Next => self.next_player(),
+      PlayerExpr::Previous => self.previous_player(),
+      PlayerExpr::Competitor => self.competitor(),
+      PlayerExpr::Turnorder(int) => self.turnorder_at(self.eval_int(int)?),
+      PlayerExpr::OwnerOf(card_position) => {
+        let card = self.eval_card(card_position)?;
+
+        match &self.locations[self.location_of(card)?].owner {
+          Owner::Player(player) => Ok(player.clone()),
+          _ => Err(RuntimeError::NoOwner(card)),
+        }
+      },
+      PlayerExpr::OwnerOfHighest(memory) => self.owner_of_extreme(&memory.to_string(), true),
+      PlayerExpr::OwnerOfLowest(memory) => self.owner_of_extreme(&memory.to_string(), false),
+    }
+  }
+
+  /// Resolves a PlayerCollection to the names of its players.
+  pub fn eval_players(&self, players: &PlayerCollection) -> Result<Vec<String>, RuntimeError> {
+    match players {
+      PlayerCollection::Player(players) => {
+        players.iter().map(|player| self.eval_player(player)).collect()
+      },
+      PlayerCollection::Others => {
+        let current = self.current_player()?;
+
+        Ok(
+          self.players
+            .iter()
+            .filter(|player| player.name != current)
+            .map(|player| player.name.clone())
+            .collect()
+        )
+      },
+      PlayerCollection::Quantifier(Quantifier::All) => {
+        Ok(self.players.iter().map(|player| player.name.clone()).collect())
+      },
+      PlayerCollection::PlayersIn => {
+        Ok(
+          self.players
+            .iter()
+            .filter(|player| player.finish.is_none() && !self.is_out_of_stage(&player.name))
+            .map(|player| player.name.clone())
+            .collect()
+        )
+      },
+      PlayerCollection::PlayersOut => {
+        Ok(
+          self.players
+            .iter()
+            .filter(|player| player.finish.is_some() || self.is_out_of_stage(&player.name))
+            .map(|player| player.name.clone())
+            .collect()
+        )
+      },
+      PlayerCollection::Quantifier(Quantifier::Any) => {
+        Err(RuntimeError::Unsupported(format!("{:?}", players)))
+      },
+    }
+  }
+
+  /// Resolves a TeamExpr to the name of a team.
+  pub fn eval_team(&self, team: &TeamExpr) -> Result<String, RuntimeError> {
+    match team {
+      TeamExpr::TeamName(name) => {
+        Ok(self.team(&name.to_string())?.name.clone())
+      },
+      TeamExpr::TeamOf(player) => {
+        let player = self.eval_player(player)?;
+
+        Ok(self.team_of(&player)?.name.clone())
+      },
+    }
+  }
+
+  /// Resolves a TeamCollection to the names of its teams.
+  pub fn eval_teams(&self, teams: &TeamCollection) -> Result<Vec<String>, RuntimeError> {
+    match teams {
+      TeamCollection::Team(teams) => {
+        teams.iter().map(|team| self.eval_team(team)).collect()
+      },
+      TeamCollection::OtherTeams => {
+        let team = self.team_of(self.current_player()?)?;
+
+        Ok(
+          self.teams
+            .iter()
+            .filter(|other| other.name != team.name)
+            .map(|other| other.name.clone())
+            .collect()
+        )
+      },
+    }
+  }
+
+  pub fn eval_string(&self, string: &StringExpr) -> Result<String, RuntimeError> {
+    match string {
+      StringExpr::ID(id) => Ok(id.to_string()),
+      StringExpr::KeyOf(key, card_position) => {
+        let key = key.to_string();
+        let card = self.eval_card(card_position)?;
+
+        self.card(card)?
+          .value(&key)
+          .map(|value| value.to_string())
+          .ok_or(RuntimeError::UnknownKey(key))
+      },
+      StringExpr::StringCollectionAt(strings, index) => {
+        let index = self.eval_int(index)?;
+        let len = strings.strings.len();
+
+        let string = usize::try_from(index)
+          .ok()
+          .and_then(|index| strings.strings.get(index))
+          .ok_or(RuntimeError::IndexOutOfRange { index, len })?;
+
+        self.eval_string(string)
+      },
+      StringExpr::Demanded => match &self.demanded {
+        Some(Demanded::String(string)) => Ok(string.clone()),
+        _ => Err(RuntimeError::NotDemanded(String::from("string"))),
+      },
+    }
+  }
+
+  /// Resolves a CardPosition to a card.
+  /// Returns None if there is no card at the position (e.g. top of an empty location).
+  pub fn eval_card_position(&self, card_position: &CardPosition, player: Option<&str>) -> Result<Option<CardId>, RuntimeError> {
+    match card_position {
+      CardPosition::Top(location) => {
+        let location = self.resolve_location(&location.to_string(), player)?;
+
+        Ok(self.locations[location].cards.first().copied())
+      },
+      CardPosition::Bottom(location) => {
+        let location = self.resolve_location(&location.to_string(), player)?;
+
+        Ok(self.locations[location].cards.last().copied())
+      },
+      CardPosition::At(location, index) => {
+        let location = self.resolve_location(&location.to_string(), player)?;
+        let index = self.eval_int(index)?;
+
+        Ok(
+          usize::try_from(index)
+            .ok()
+            .and_then(|index| self.locations[location].cards.get(index))
+            .copied()
+        )
+      },
+      CardPosition::MaxPrec(cardset, precedence) => {
+        self.eval_card_by_precedence(cardset, precedence, player, Ordering::Greater)
+      },
+      CardPosition::MinPrec(cardset, precedence) => {
+        self.eval_card_by_precedence(cardset, precedence, player, Ordering::Less)
+      },
+      CardPosition::MaxPoint(cardset, point_map) => {
+        self.eval_card_by_points(cardset, point_map, player, |points, best| points > best)
+      },
+      CardPosition::MinPoint(cardset, point_map) => {
+        self.eval_card_by_points(cardset, point_map, player, |points, best| points < best)
+      },
+      CardPosition::Demanded => match self.demanded {
+        Some(Demanded::Card(card)) => Ok(Some(card)),
+        _ => Err(RuntimeError::NotDemanded(String::from("card"))),
+      },
+    }
+  }
+
+  /// Picks the highest (Greater) or lowest (Less) card of the precedence, the first card wins ties.
+  fn eval_card_by_precedence(
+    &self,
+    cardset: &CardSet,
+    precedence: &Precedence,
+    player: Option<&str>,
+    better: Ordering
+  ) -> Result<Option<CardId>, RuntimeError> {
+    let precedence = self.precedence(&precedence.to_string())?;
+
+    let mut best: Option<CardId> = None;
+    for card in self.eval_cardset_of(cardset, player)? {
+      let is_better = match best {
+        Some(best) => precedence.compare(self.card(card)?, self.card(best)?) == better,
+        None => true,
+      };
+      if is_better {
+        best = Some(card);
+      }
+    }
+
+    Ok(best)
+  }
+
+  /// Picks the card with the most extreme points, the first card wins ties.
+  fn eval_card_by_points(
+    &self,
+    cardset: &CardSet,
+    point_map: &PointMap,
+    player: Option<&str>,
+    better: fn(i32, i32) -> bool
+  ) -> Result<Option<CardId>, RuntimeError> {
+    let point_map = self.point_map(&point_map.to_string())?;
+
+    let mut best: Option<(CardId, i32)> = None;
+    for card in self.eval_cardset_of(cardset, player)? {
+      let points = point_map.points_of(self.card(card)?);
+      if best.is_none_or(|(_, best)| better(points, best)) {
+        best = Some((card, points));
+      }
+    }
+
+    Ok(best.map(|(card, _)| card))
+  }
+
+  /// Resolves a CardPosition that has to refer to a card.
+  pub fn eval_card(&self, card_position: &CardPosition) -> Result<CardId, RuntimeError> {
+    self.eval_card_position(card_position, None)?
+      .ok_or_else(|| RuntimeError::NoCard(format!("{:?}", card_position)))
+  }
+
+  /// Resolves a Group to its cards. The player owns the locations of the Group.
+  pub fn eval_group(&self, group: &Group, player: Option<&str>) -> Result<Vec<CardId>, RuntimeError> {
+    match group {
+      Group::Location(location) => {
+        let location = self.resolve_location(&location.to_string(), player)?;
+
+        Ok(self.locations[location].cards.clone())
+      },
+      Group::LocationCollection(locations) => {
+        let mut cards = Vec::new();
+        for location in locations.locations.iter() {
+          let location = self.resolve_location(&location.to_string(), player)?;
+          cards.extend_from_slice(&self.locations[location].cards);
+        }
+
+        Ok(cards)
+      },
+      Group::LocationWhere(location, filter) => {
+        let cards = self.eval_group(&Group::Location(location.clone()), player)?;
+
+        self.combo_members(&cards, filter)
+      },
+      Group::LocationCollectionWhere(locations, filter) => {
+        let cards = self.eval_group(&Group::LocationCollection(locations.clone()), player)?;
+
+        self.combo_members(&cards, filter)
+      },
+      Group::ComboInLocation(combo, location) => {
+        let cards = self.eval_group(&Group::Location(location.clone()), player)?;
+
+        self.combo_members(&cards, self.combo(&combo.to_string())?)
+      },
+      Group::ComboInLocationCollection(combo, locations) => {
+        let cards = self.eval_group(&Group::LocationCollection(locations.clone()), player)?;
+
+        self.combo_members(&cards, self.combo(&combo.to_string())?)
+      },
+      Group::NotComboInLocation(combo, location) => {
+        let cards = self.eval_group(&Group::Location(location.clone()), player)?;
+        let members = self.combo_members(&cards, self.combo(&combo.to_string())?)?;
+
+        Ok(cards.into_iter().filter(|card| !members.contains(card)).collect())
+      },
+      Group::NotComboInLocationCollection(combo, locations) => {
+        let cards = self.eval_group(&Group::LocationCollection(locations.clone()), player)?;
+        let members = self.combo_members(&cards, self.combo(&combo.to_string())?)?;
+
+        Ok(cards.into_iter().filter(|card| !members.contains(card)).collect())
+      },
+      Group::CardPosition(card_position) => {
+        Ok(self.eval_card_position(card_position, player)?.into_iter().collect())
+      },
+    }
+  }
+
+  pub fn eval_cardset(&self, cardset: &CardSet) -> Result<Vec<CardId>, RuntimeError> {
+    self.eval_cardset_of(cardset, None)
+  }
+
+  /// Resolves a CardSet, a Group without a player belongs to the given player.
+  pub fn eval_cardset_of(&self, cardset: &CardSet, player: Option<&str>) -> Result<Vec<CardId>, RuntimeError> {
+    match cardset {
+      CardSet::Group(group) => {
+        self.eval_group(group, player)
+      },
+      CardSet::GroupOfPlayer(group, player) => {
+        let player = self.eval_player(player)?;
+
+        self.eval_group(group, Some(&player))
+      },
+      CardSet::GroupOfPlayerCollection(group, players) => {
+        let mut cards = Vec::new();
+        for player in self.eval_players(players)? {
+          cards.extend(self.eval_group(group, Some(&player))?);
+        }
+
+        Ok(cards)
+      },
+    }
+  }
+
+  pub fn eval_int(&self, int: &IntExpr) -> Result<i32, RuntimeError> {
+    match int {
+      IntExpr::Int(int) => Ok(*int),
+      IntExpr::IntOp(left, op, right) => {
+        let left = self.eval_int(left)?;
+        let right = self.eval_int(right)?;
+
+        let result = match op {
+          Op::Plus => left.checked_add(right),
+          Op::Minus => left.checked_sub(right),
+          Op::Mul => left.checked_mul(right),
+          Op::Div | Op::Mod if right == 0 => return Err(RuntimeError::DivisionByZero),
+          Op::Div => left.checked_div(right),
+          Op::Mod => left.checked_rem(right),
+        };
+
+        result.ok_or(RuntimeError::Overflow)
+      },
+      IntExpr::IntCollectionAt(ints, index) => {
+        let index = self.eval_int(index)?;
+        let len = ints.ints.len();
+
+        let int = usize::try_from(index)
+          .ok()
+          .and_then(|index| ints.ints.get(index))
+          .ok_or(RuntimeError::IndexOutOfRange { index, len })?;
+
+        self.eval_int(int)
+      },
+      IntExpr::SizeOf(collection) => {
+        let size = self.eval_size(collection)?;
+
+        i32::try_from(size).map_err(|_| RuntimeError::Overflow)
+      },
+      IntExpr::SumOfIntCollection(ints) => {
+        self.eval_ints(ints)?
+          .into_iter()
+          .try_fold(0, |sum: i32, int| sum.checked_add(int))
+          .ok_or(RuntimeError::Overflow)
+      },
+      IntExpr::SumOfCardSet(cardset, point_map) => {
+        self.eval_points(cardset, point_map)?
+          .into_iter()
+          .try_fold(0, |sum: i32, points| sum.checked_add(points))
+          .ok_or(RuntimeError::Overflow)
+      },
+      IntExpr::MinOf(cardset, point_map) => {
+        self.eval_points(cardset, point_map)?
+          .into_iter()
+          .min()
+          .ok_or(RuntimeError::EmptyCollection)
+      },
+      IntExpr::MaxOf(cardset, point_map) => {
+        self.eval_points(cardset, point_map)?
+          .into_iter()
+          .max()
+          .ok_or(RuntimeError::EmptyCollection)
+      },
+      IntExpr::MinIntCollection(ints) => {
+        self.eval_ints(ints)?
+          .into_iter()
+          .min()
+          .ok_or(RuntimeError::EmptyCollection)
+      },
+      IntExpr::MaxIntCollection(ints) => {
+        self.eval_ints(ints)?
+          .into_iter()
+          .max()
+          .ok_or(RuntimeError::EmptyCollection)
+      },
+      IntExpr::StageRoundCounter => Ok(self.stage()?.rounds),
+      IntExpr::Memory(memory) => self.memory_int(&memory.to_string(), None),
+      IntExpr::Demanded => match self.demanded {
+        Some(Demanded::Int(int)) => Ok(int),
+        _ => Err(RuntimeError::NotDemanded(String::from("int"))),
+      },
+    }
+  }
+
+  pub fn eval_ints(&self, ints: &IntCollection) -> Result<Vec<i32>, RuntimeError> {
+    ints.ints.iter().map(|int| self.eval_int(int)).collect()
+  }
+
+  /// Number of elements of a Collection.
+  pub fn eval_size(&self, collection: &Collection) -> Result<usize, RuntimeError> {
+    match collection {
+      Collection::IntCollection(ints) => Ok(ints.ints.len()),
+      Collection::StringCollection(strings) => Ok(strings.strings.len()),
+      Collection::LocationCollection(locations) => Ok(locations.locations.len()),
+      Collection::PlayerCollection(players) => Ok(self.eval_players(players)?.len()),
+      Collection::TeamCollection(teams) => Ok(self.eval_teams(teams)?.len()),
+      Collection::CardSet(cardset) => Ok(self.eval_cardset(cardset)?.len()),
+    }
+  }
+
+  /// Points of every card of the CardSet.
+  pub fn eval_points(&self, cardset: &CardSet, point_map: &PointMap) -> Result<Vec<i32>, RuntimeError> {
+    let point_map = self.point_map(&point_map.to_string())?;
+
+    self.eval_cardset(cardset)?
+      .into_iter()
+      .map(|card| Ok(point_map.points_of(self.card(card)?)))
+      .collect()
+  }
+
+  pub fn eval_bool(&self, bool_expr: &BoolExpr) -> Result<bool, RuntimeError> {
+    match bool_expr {
+      BoolExpr::IntCmp(left, op, right) => {
+        let left = self.eval_int(left)?;
+        let right = self.eval_int(right)?;
+
+        Ok(
+          match op {
+            IntCmpOp::Eq => left == right,
+            IntCmpOp::Neq => left != right,
+            IntCmpOp::Gt => left > right,
+            IntCmpOp::Lt => left < right,
+            IntCmpOp::Ge => left >= right,
+            IntCmpOp::Le => left <= right,
+          }
+        )
+      },
+      BoolExpr::StringEq(left, right) => {
+        Ok(self.eval_string(left)? == self.eval_string(right)?)
+      },
+      BoolExpr::StringNeq(left, right) => {
+        Ok(self.eval_string(left)? != self.eval_string(right)?)
+      },
+      BoolExpr::CardSetEq(left, right) => {
+        Ok(same_cards(self.eval_cardset(left)?, self.eval_cardset(right)?))
+      },
+      BoolExpr::CardSetNeq(left, right) => {
+        Ok(!same_cards(self.eval_cardset(left)?, self.eval_cardset(right)?))
+      },
+      BoolExpr::CardSetIsEmpty(cardset) => {
+        Ok(self.eval_cardset(cardset)?.is_empty())
+      },
+      BoolExpr::CardSetIsNotEmpty(cardset) => {
+        Ok(!self.eval_cardset(cardset)?.is_empty())
+      },
+      BoolExpr::PlayerEq(left, right) => {
+        Ok(self.eval_player(left)? == self.eval_player(right)?)
+      },
+      BoolExpr::PlayerNeq(left, right) => {
+        Ok(self.eval_player(left)? != self.eval_player(right)?)
+      },
+      BoolExpr::TeamEq(left, right) => {
+        Ok(self.eval_team(left)? == self.eval_team(right)?)
+      },
+      BoolExpr::TeamNeq(left, right) => {
+        Ok(self.eval_team(left)? != self.eval_team(right)?)
+      },
+      BoolExpr::And(left, right) => {
+        Ok(self.eval_bool(left)? && self.eval_bool(right)?)
+      },
+      BoolExpr::Or(left, right) => {
+        Ok(self.eval_bool(left)? || self.eval_bool(right)?)
+      },
+      BoolExpr::Not(bool_expr) => {
+        Ok(!self.eval_bool(bool_expr)?)
+      },
+      BoolExpr::OutOfStagePlayer(player) => {
+        Ok(self.is_out_of_stage(&self.eval_player(player)?))
+      },
+      BoolExpr::OutOfGamePlayer(player) => {
+        self.is_out_of_game(&self.eval_player(player)?)
+      },
+      BoolExpr::OutOfStageCollection(players) => {
+        Ok(
+          self.eval_players(players)?
+            .iter()
+            .all(|player| self.is_out_of_stage(player))
+        )
+      },
+      BoolExpr::OutOfGameCollection(players) => {
+        for player in self.eval_players(players)? {
+          if !self.is_out_of_game(&player)? {
+            return Ok(false)
+          }
+        }
+
+        Ok(true)
+      },
+    }
+  }
+
+  /// Checks if the EndCondition of the innermost stage is reached.
+  pub fn eval_end_condition(&self, end_condition: &EndCondition) -> Result<bool, RuntimeError> {
+    match end_condition {
+      EndCondition::UntilBool(bool_expr) => {
+        self.eval_bool(bool_expr)
+      },
+      EndCondition::UntilBoolAndRep(bool_expr, reps) => {
+        Ok(self.eval_bool(bool_expr)? && self.eval_reps(reps)?)
+      },
+      EndCondition::UntilBoolOrRep(bool_expr, reps) => {
+        Ok(self.eval_bool(bool_expr)? || self.eval_reps(reps)?)
+      },
+      EndCondition::UntilRep(reps) => {
+        self.eval_reps(reps)
+      },
+      EndCondition::UntilEnd => Ok(false),
+    }
+  }
+
+  fn eval_reps(&self, reps: &Repititions) -> Result<bool, RuntimeError> {
+    Ok(self.stage()?.rounds >= self.eval_int(&reps.times)?)
+  }
+}
+
+/// Two card sets are equal if they contain the same cards, regardless of the order.
+fn same_cards(mut left: Vec<CardId>, mut right: Vec<CardId>) -> bool {
+  left.sort();
+  right.sort();
+
+  left == right
+}
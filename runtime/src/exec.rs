@@ -0,0 +1,349 @@
+use ast::ast::*;
+
+use crate::error::RuntimeError;
+use crate::memory::MemoryValue;
+use crate::points::PointMapState;
+use crate::precedence::PrecedenceState;
+use crate::state::{Card, CardStatus, GameState, LocationState, Owner, Player, Team};
+
+impl GameState {
+  /// Executes a single Rule on the game state.
+  pub fn execute(&mut self, rule: &Rule) -> Result<(), RuntimeError> {
+    match rule {
+      Rule::CreatePlayer(names) => {
+        for name in names.iter() {
+          self.players.push(
+            Player {
+              name: name.to_string(),
+              score: 0,
+              finish: None,
+            }
+          );
+        }
+      },
+      Rule::CreateTeam(name, players) => {
+        let players = players.iter().map(|player| player.to_string()).collect::<Vec<_>>();
+        for player in players.iter() {
+          self.player(player)?;
+        }
+
+        self.teams.push(
+          Team {
+            name: name.to_string(),
+            players,
+          }
+        );
+      },
+      Rule::CreateTurnorder(players) => {
+        let players = players.iter().map(|player| player.to_string()).collect::<Vec<_>>();
+        for player in players.iter() {
+          self.player(player)?;
+        }
+
+        self.turnorder = players;
+        self.current = 0;
+      },
+      Rule::CreateTurnorderRandom(players) => {
+        let mut players = players.iter().map(|player| player.to_string()).collect::<Vec<_>>();
+        for player in players.iter() {
+          self.player(player)?;
+        }
+
+        self.rng.shuffle(&mut players);
+        self.turnorder = players;
+        self.current = 0;
+      },
+      Rule::CreateLocationOnPlayerCollection(location, players) => {
+        for player in self.eval_players(players)? {
+          self.create_location(location.to_string(), Owner::Player(player))?;
+        }
+      },
+      Rule::CreateLocationOnTeamCollection(location, teams) => {
+        for team in self.eval_teams(teams)? {
+          self.create_location(location.to_string(), Owner::Team(team))?;
+        }
+      },
+      Rule::CreateLocationOnTable(location) => {
+        self.create_location(location.to_string(), Owner::Table)?;
+      },
+      Rule::CreateLocationCollectionOnPlayerCollection(locations, players) => {
+        for player in self.eval_players(players)? {
+          for location in locations.locations.iter() {
+            self.create_location(location.to_string(), Owner::Player(player.clone()))?;
+          }
+        }
+      },
+      Rule::CreateLocationCollectionOnTeamCollection(locations, teams) => {
+        for team in self.eval_teams(teams)? {
+          for location in locations.locations.iter() {
+            self.create_location(location.to_string(), Owner::Team(team.clone()))?;
+          }
+        }
+      },
+      Rule::CreateLocationCollectionOnTable(locations) => {
+        for location in locations.locations.iter() {
+          self.create_location(location.to_string(), Owner::Table)?;
+        }
+      },
+      Rule::CreateCardOnLocation(location, types) => {
+        self.create_cards(&location.to_string(), types)?;
+      },
+      Rule::CreateTokenOnLocation(amount, token, location) => {
+        self.create_tokens(amount, &token.to_string(), &location.to_string())?;
+      },
+      Rule::CreateMemoryIntPlayerCollection(memory, int, players) => {
+        let value = MemoryValue::Int(self.eval_int(int)?);
+        for player in self.eval_players(players)? {
+          self.create_memory(&memory.to_string(), Owner::Player(player), Some(value.clone()))?;
+        }
+      },
+      Rule::CreateMemoryStringPlayerCollection(memory, string, players) => {
+        let value = MemoryValue::String(self.eval_string(string)?);
+        for player in self.eval_players(players)? {
+          self.create_memory(&memory.to_string(), Owner::Player(player), Some(value.clone()))?;
+        }
+      },
+      Rule::CreateMemoryPlayerCollection(memory, players) => {
+        for player in self.eval_players(players)? {
+          self.create_memory(&memory.to_string(), Owner::Player(player), None)?;
+        }
+      },
+      Rule::CreateMemoryIntTable(memory, int) => {
+        let value = MemoryValue::Int(self.eval_int(int)?);
+        self.create_memory(&memory.to_string(), Owner::Table, Some(value))?;
+      },
+      Rule::CreateMemoryStringTable(memory, string) => {
+        let value = MemoryValue::String(self.eval_string(string)?);
+        self.create_memory(&memory.to_string(), Owner::Table, Some(value))?;
+      },
+      Rule::CreateMemoryTable(memory) => {
+        self.create_memory(&memory.to_string(), Owner::Table, None)?;
+      },
+      Rule::CreatePointMap(name, on_key_point) => {
+        let key = on_key_point.key.to_string();
+        let mut points = Vec::new();
+        for pair in on_key_point.value_int_vec.iter() {
+          points.push((key.clone(), pair.value.to_string(), self.eval_int(&pair.int)?));
+        }
+
+        self.point_maps.push(
+          PointMapState {
+            name: name.to_string(),
+            points,
+          }
+        );
+      },
+      Rule::CreatePrecedence(name, on_key_prec) => {
+        let key = on_key_prec.key.to_string();
+
+        self.precedences.push(
+          PrecedenceState {
+            name: name.to_string(),
+            order: on_key_prec.values.iter().map(|value| (key.clone(), value.to_string())).collect(),
+          }
+        );
+      },
+      Rule::CreatePrecedencePairs(name, key_value_pairs) => {
+        self.precedences.push(
+          PrecedenceState {
+            name: name.to_string(),
+            order: key_value_pairs.key_value
+              .iter()
+              .map(|(key, value)| (key.to_string(), value.to_string()))
+              .collect(),
+          }
+        );
+      },
+      Rule::CreatePointMapPairs(name, key_value_int) => {
+        let mut points = Vec::new();
+        for (key, value, int) in key_value_int.key_value_int_vec.iter() {
+          points.push((key.to_string(), value.to_string(), self.eval_int(int)?));
+        }
+
+        self.point_maps.push(
+          PointMapState {
+            name: name.to_string(),
+            points,
+          }
+        );
+      },
+      Rule::CreateCombo(name, filter) => {
+        self.combos.push((name.to_string(), filter.clone()));
+      },
+      Rule::PlayerOutOfStageAction(player) => {
+        let player = self.eval_player(player)?;
+        self.set_out_of_stage(player)?;
+      },
+      Rule::PlayerCollectionOutOfStageAction(players) => {
+        for player in self.eval_players(players)? {
+          self.set_out_of_stage(player)?;
+        }
+      },
+      Rule::PlayerOutOfGameSuccAction(player) => {
+        let player = self.eval_player(player)?;
+        self.finish(&player, true)?;
+      },
+      Rule::PlayerOutOfGameFailAction(player) => {
+        let player = self.eval_player(player)?;
+        self.finish(&player, false)?;
+      },
+      Rule::PlayerCollectionOutOfGameSuccAction(players) => {
+        for player in self.eval_players(players)? {
+          self.finish(&player, true)?;
+        }
+      },
+      Rule::PlayerCollectionOutOfGameFailAction(players) => {
+        for player in self.eval_players(players)? {
+          self.finish(&player, false)?;
+        }
+      },
+      Rule::SetMemoryInt(memory, int) => {
+        let value = MemoryValue::Int(self.eval_int(int)?);
+        self.set_memory(&memory.to_string(), value)?;
+      },
+      Rule::SetMemoryString(memory, string) => {
+        let value = MemoryValue::String(self.eval_string(string)?);
+        self.set_memory(&memory.to_string(), value)?;
+      },
+      Rule::SetMemoryCollection(memory, collection) => {
+        let value = MemoryValue::Collection(self.eval_collection(collection)?);
+        self.set_memory(&memory.to_string(), value)?;
+      },
+      Rule::CycleAction(player) => {
+        let player = self.eval_player(player)?;
+        self.current = self.turnorder_index(&player)?;
+      },
+      Rule::EndTurn => {
+        // the FSM jumps to the end of the round, the turn passes to the next player
+        self.pass_turn()?;
+      },
+      Rule::EndStage => {
+        // the ExitStage transition that follows leaves the stage
+        self.stage()?;
+      },
+      Rule::EndGameWithWinner(player) => {
+        self.winners = vec![self.eval_player(player)?];
+        self.game_over = true;
+      },
+      Rule::ScoreRule(score_rule) => {
+        self.execute_score(score_rule)?;
+      },
+      Rule::WinnerRule(winner_rule) => {
+        self.winners = self.resolve_winners(winner_rule)?;
+      },
+      Rule::BidAction(_) | Rule::BidActionMemory(_, _) => {
+        self.execute_bid(rule, None)?;
+      },
+      Rule::ClassicMove(_) | Rule::DealMove(_) | Rule::ExchangeMove(_) | Rule::TokenMove(_) => {
+        self.execute_move(rule, None)?;
+      },
+      Rule::ShuffleAction(cardset) => {
+        self.shuffle(cardset)?;
+      },
+      Rule::FlipAction(cardset, status) => {
+        for card in self.eval_cardset(cardset)? {
+          self.cards[card].status = status.into();
+        }
+      },
+      Rule::DemandCardPositionAction(_) | Rule::DemandStringAction(_) | Rule::DemandIntAction(_) => {
+        self.execute_demand(rule, None)?;
+      },
+    }
+
+    Ok(())
+  }
+
+  fn create_location(&mut self, name: String, owner: Owner) -> Result<(), RuntimeError> {
+    if self.location_index(&name, &owner).is_some() {
+      return Err(RuntimeError::DuplicateLocation(name))
+    }
+
+    self.locations.push(
+      LocationState {
+        name,
+        owner,
+        cards: Vec::new(),
+        tokens: Vec::new(),
+      }
+    );
+
+    Ok(())
+  }
+
+  /// Creates the cartesian product of the Types on every location with the name.
+  /// The cards are created face down, the first key varies slowest.
+  fn create_cards(&mut self, location: &str, types: &Types) -> Result<(), RuntimeError> {
+    let mut combinations: Vec<Vec<(String, String)>> = vec![Vec::new()];
+    for (key, values) in types.types.iter() {
+      combinations = combinations
+        .into_iter()
+        .flat_map(|attributes| {
+          values.iter().map(move |value| {
+            let mut attributes = attributes.clone();
+            attributes.push((key.to_string(), value.to_string()));
+
+            attributes
+          })
+        })
+        .collect();
+    }
+
+    let locations = (0..self.locations.len())
+      .filter(|index| self.locations[*index].name == location)
+      .collect::<Vec<_>>();
+    if locations.is_empty() {
+      return Err(RuntimeError::UnknownLocation(location.to_string()))
+    }
+
+    for index in locations {
+      for attributes in combinations.iter() {
+        let id = self.cards.len();
+        self.cards.push(
+          Card {
+            id,
+            attributes: attributes.clone(),
+            status: CardStatus::FaceDown,
+          }
+        );
+        self.locations[index].cards.push(id);
+      }
+    }
+
+    Ok(())
+  }
+
+  /// Shuffles the cards of the CardSet within every location, cards that are not part of it keep their place.
+  fn shuffle(&mut self, cardset: &CardSet) -> Result<(), RuntimeError> {
+    let cards = self.eval_cardset(cardset)?;
+
+    for location in self.target_locations(cardset)? {
+      let places = self.locations[location].cards
+        .iter()
+        .enumerate()
+        .filter(|(_, card)| cards.contains(card))
+        .map(|(place, _)| place)
+        .collect::<Vec<_>>();
+
+      let mut shuffled = places
+        .iter()
+        .map(|place| self.locations[location].cards[*place])
+        .collect::<Vec<_>>();
+      self.rng.shuffle(&mut shuffled);
+
+      for (place, card) in places.into_iter().zip(shuffled) {
+        self.locations[location].cards[place] = card;
+      }
+    }
+
+    Ok(())
+  }
+
+  fn set_out_of_stage(&mut self, player: String) -> Result<(), RuntimeError> {
+    let stage = self.stage_mut()?;
+    if !stage.out_of_stage.contains(&player) {
+      stage.out_of_stage.push(player);
+    }
+
+    Ok(())
+  }
+}
@@ -0,0 +1,300 @@
+use ast::ast::*;
+use ir::fsm::TransitionID;
+use serde::{Deserialize, Serialize};
+
+use crate::bidding::BidRange;
+use crate::error::RuntimeError;
+use crate::state::{CardId, GameState};
+
+/// Legal answers to an InputRequest.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Options {
+  /// One of the transitions of a ChoiceRule.
+  Choice(Vec<TransitionID>),
+  /// Enter or skip the body of an OptionalRule.
+  Optional { enter: TransitionID, skip: TransitionID },
+  /// Distinct cards, as many as one of the counts (free Quantity of a move or exchange).
+  Cards { cards: Vec<CardChoice>, counts: Vec<usize> },
+  /// One of the amounts (cards dealt to every player, tokens placed).
+  Amount(Vec<usize>),
+  /// A bid in the range.
+  Bid(BidRange),
+  /// One of the cards (DemandCardPositionAction).
+  Card(Vec<CardChoice>),
+  /// One of the strings (DemandStringAction).
+  String(Vec<String>),
+  /// An int from min to max, both included (DemandIntAction).
+  Int { min: i32, max: i32 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnswerType {
+  Transition,
+  Cards,
+  Amount,
+  Card,
+  String,
+  Int,
+}
+
+/// Answer of a player to an InputRequest.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Answer {
+  Transition(TransitionID),
+  Cards(Vec<CardChoice>),
+  Amount(usize),
+  Card(CardChoice),
+  String(String),
+  Int(i32),
+}
+
+/// A card offered by a demand or a free Quantity of a move.
+/// Cards the asked player can not see are offered by their place among the offered cards,
+/// the ids follow the order the cards were created in and would reveal them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum CardChoice {
+  Card(CardId),
+  /// Index among the offered cards, for a location the top card has index 0.
+  Hidden(usize),
+}
+
+impl Answer {
+  pub fn answer_type(&self) -> AnswerType {
+    match self {
+      Answer::Transition(_) => AnswerType::Transition,
+      Answer::Cards(_) => AnswerType::Cards,
+      Answer::Amount(_) => AnswerType::Amount,
+      Answer::Card(_) => AnswerType::Card,
+      Answer::String(_) => AnswerType::String,
+      Answer::Int(_) => AnswerType::Int,
+    }
+  }
+}
+
+impl Options {
+  /// Type of the answers, bids are answered with an Int.
+  pub fn answer_type(&self) -> AnswerType {
+    match self {
+      Options::Choice(_) | Options::Optional { .. } => AnswerType::Transition,
+      Options::Cards { .. } => AnswerType::Cards,
+      Options::Amount(_) => AnswerType::Amount,
+      Options::Card(_) => AnswerType::Card,
+      Options::String(_) => AnswerType::String,
+      Options::Bid(_) | Options::Int { .. } => AnswerType::Int,
+    }
+  }
+
+  /// Checks if the answer is one of the legal options.
+  pub fn accepts(&self, answer: &Answer) -> bool {
+    match (self, answer) {
+      (Options::Choice(transitions), Answer::Transition(transition)) => transitions.contains(transition),
+      (Options::Optional { enter, skip }, Answer::Transition(transition)) => transition == enter || transition == skip,
+      (Options::Cards { cards, counts }, Answer::Cards(picked)) => {
+        let mut distinct = picked.clone();
+        distinct.sort();
+        distinct.dedup();
+
+        distinct.len() == picked.len()
+          && counts.contains(&picked.len())
+          && picked.iter().all(|card| cards.contains(card))
+      },
+      (Options::Amount(amounts), Answer::Amount(amount)) => amounts.contains(amount),
+      (Options::Bid(range), Answer::Int(bid)) => range.contains(*bid),
+      (Options::Card(cards), Answer::Card(card)) => cards.contains(card),
+      (Options::String(strings), Answer::String(string)) => strings.contains(string),
+      (Options::Int { min, max }, Answer::Int(int)) => min <= int && int <= max,
+      _ => false,
+    }
+  }
+
+  /// The answer of a demand that leaves only one legal option.
+  fn single(&self) -> Option<Answer> {
+    match self {
+      Options::Card(cards) if cards.len() == 1 => Some(Answer::Card(cards[0])),
+      Options::String(strings) if strings.len() == 1 => Some(Answer::String(strings[0].clone())),
+      Options::Int { min, max } if min == max => Some(Answer::Int(*min)),
+      _ => None,
+    }
+  }
+}
+
+/// A decision the runtime needs from a player before the game can go on.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InputRequest {
+  /// The player that is asked, always the current player.
+  pub player: String,
+  pub options: Options,
+}
+
+impl InputRequest {
+  pub fn answer_type(&self) -> AnswerType {
+    self.options.answer_type()
+  }
+
+  pub fn accepts(&self, answer: &Answer) -> bool {
+    self.options.accepts(answer)
+  }
+}
+
+/// The answer to the last demand, later rules read it with `demanded`.
+#[derive(Debug, Clone, PartialEq, Hash, Serialize, Deserialize)]
+pub enum Demanded {
+  Card(CardId),
+  String(String),
+  Int(i32),
+}
+
+impl GameState {
+  /// The input the current player has to give before the rule can be executed.
+  /// Rules without a free Quantity or demand, or with a single legal answer, return None.
+  pub fn input_request(&self, rule: &Rule) -> Result<Option<InputRequest>, RuntimeError> {
+    let options = match rule {
+      Rule::ClassicMove(_) | Rule::DealMove(_) | Rule::ExchangeMove(_) | Rule::TokenMove(_) => {
+        self.move_options(rule)?
+      },
+      Rule::BidAction(quantity) | Rule::BidActionMemory(_, quantity) => self.bid_options(quantity)?,
+      Rule::DemandCardPositionAction(_) | Rule::DemandStringAction(_) | Rule::DemandIntAction(_) => {
+        Some(self.demand_options(rule)?).filter(|options| options.single().is_none())
+      },
+      _ => None,
+    };
+
+    match options {
+      Some(options) => Ok(Some(
+        InputRequest {
+          player: self.current_player()?.to_string(),
+          options,
+        }
+      )),
+      None => Ok(None),
+    }
+  }
+
+  /// Executes a rule with the answer to its InputRequest.
+  pub fn execute_answer(&mut self, rule: &Rule, answer: &Answer) -> Result<(), RuntimeError> {
+    match rule {
+      Rule::BidAction(_) | Rule::BidActionMemory(_, _) => self.execute_bid(rule, Some(answer)),
+      Rule::DemandCardPositionAction(_) | Rule::DemandStringAction(_) | Rule::DemandIntAction(_) => {
+        self.execute_demand(rule, Some(answer))
+      },
+      _ => self.execute_move(rule, Some(answer)),
+    }
+  }
+
+  /// Legal answers of a demand.
+  /// `demand top(hand)` asks for any card of the location of the position,
+  /// `demand max(hand) using ...` for any card of the CardSet.
+  /// Cards the current player can not see are offered as `CardChoice::Hidden`.
+  ///
+  /// `demand Rank` asks for one of the values the key has on any card of the game,
+  /// wherever the cards are: the player names a value, like asking for a rank they want.
+  /// Any other string is the only option.
+  ///
+  /// `demand 10` asks for an int from 0 to 10, both included.
+  /// A negative int leaves no legal answer.
+  pub fn demand_options(&self, rule: &Rule) -> Result<Options, RuntimeError> {
+    let options = match rule {
+      Rule::DemandCardPositionAction(card_position) => Options::Card(self.card_choices(card_position)?),
+      Rule::DemandStringAction(string) => Options::String(self.demand_strings(string)?),
+      Rule::DemandIntAction(int) => Options::Int { min: 0, max: self.eval_int(int)? },
+      _ => return Err(RuntimeError::Unsupported(format!("{:?}", rule))),
+    };
+
+    let empty = match &options {
+      Options::Card(cards) => cards.is_empty(),
+      Options::String(strings) => strings.is_empty(),
+      Options::Int { min, max } => max < min,
+      _ => false,
+    };
+    if empty {
+      return Err(RuntimeError::NoLegalAnswer)
+    }
+
+    Ok(options)
+  }
+
+  /// The cards of the demand as the current player can name them.
+  fn card_choices(&self, card_position: &CardPosition) -> Result<Vec<CardChoice>, RuntimeError> {
+    self.choices(&self.demand_cards(card_position)?)
+  }
+
+  /// The cards as the current player can name them, by id if they can see them and by index otherwise.
+  pub(crate) fn choices(&self, cards: &[CardId]) -> Result<Vec<CardChoice>, RuntimeError> {
+    let player = self.current_player()?;
+
+    let mut choices = Vec::new();
+    for (index, card) in cards.iter().copied().enumerate() {
+      if self.is_visible(card, player)? {
+        choices.push(CardChoice::Card(card));
+      } else {
+        choices.push(CardChoice::Hidden(index));
+      }
+    }
+
+    Ok(choices)
+  }
+
+  fn demand_cards(&self, card_position: &CardPosition) -> Result<Vec<CardId>, RuntimeError> {
+    match card_position {
+      CardPosition::At(location, _) | CardPosition::Top(location) | CardPosition::Bottom(location) => {
+        let location = self.resolve_location(&location.to_string(), None)?;
+
+        Ok(self.locations[location].cards.clone())
+      },
+      CardPosition::MaxPrec(cardset, _)
+        | CardPosition::MinPrec(cardset, _)
+        | CardPosition::MaxPoint(cardset, _)
+        | CardPosition::MinPoint(cardset, _) => self.eval_cardset(cardset),
+      CardPosition::Demanded => Ok(vec![self.eval_card(card_position)?]),
+    }
+  }
+
+  fn demand_strings(&self, string: &StringExpr) -> Result<Vec<String>, RuntimeError> {
+    if let StringExpr::ID(id) = string {
+      let key = id.to_string();
+      let mut values = Vec::new();
+      for card in self.cards.iter() {
+        if let Some(value) = card.value(&key)
+          && !values.iter().any(|known| known == value) {
+          values.push(value.to_string());
+        }
+      }
+
+      if !values.is_empty() {
+        return Ok(values)
+      }
+    }
+
+    Ok(vec![self.eval_string(string)?])
+  }
+
+  /// Validates the answer of the current player to a demand and stores it as the demanded value.
+  pub fn execute_demand(&mut self, rule: &Rule, answer: Option<&Answer>) -> Result<(), RuntimeError> {
+    let options = self.demand_options(rule)?;
+    let answer = match (options.single(), answer) {
+      (Some(single), _) => single,
+      (None, Some(answer)) if options.accepts(answer) => answer.clone(),
+      (None, Some(_)) => return Err(RuntimeError::InvalidAnswer),
+      (None, None) => return Err(RuntimeError::UndecidedDemand),
+    };
+
+    self.demanded = match answer {
+      Answer::Card(choice) => {
+        let card = match (choice, rule) {
+          (CardChoice::Card(card), _) => card,
+          (CardChoice::Hidden(index), Rule::DemandCardPositionAction(card_position)) => {
+            self.demand_cards(card_position)?[index]
+          },
+          _ => return Err(RuntimeError::InvalidAnswer),
+        };
+
+        Some(Demanded::Card(card))
+      },
+      Answer::String(string) => Some(Demanded::String(string)),
+      Answer::Int(int) => Some(Demanded::Int(int)),
+      _ => return Err(RuntimeError::InvalidAnswer),
+    };
+
+    Ok(())
+  }
+}
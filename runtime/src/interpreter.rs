@@ -0,0 +1,323 @@
+use ast::ast::*;
+use ir::fsm::*;
+
+use crate::error::RuntimeError;
+use crate::input::{Answer, InputRequest, Options};
+use crate::replay::{Event, diff};
+use crate::rng::Rng;
+use crate::state::{GameState, StageFrame};
+use crate::undo::History;
+
+/// Result of a single step of the Interpreter.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Step {
+  /// The transition was taken.
+  Transition(TransitionID),
+  /// A player has to answer a choice, optional rule, demand or free Quantity first.
+  Input(InputRequest),
+  /// The game is over.
+  Finished,
+}
+
+/// Walks the FSM of a game and applies its transitions to a GameState.
+#[derive(Debug, Clone)]
+pub struct Interpreter {
+  fsm: FSM,
+  pub(crate) current_state: StateID,
+  pub state: GameState,
+  pub(crate) log: Vec<Event>,
+  /// The state as of the last Diff of the log, None if the log records no Diffs.
+  pub(crate) logged: Option<GameState>,
+  pub(crate) history: History,
+}
+
+impl Interpreter {
+  pub fn new(fsm: FSM) -> Self {
+    Interpreter::with_seed(fsm, 0)
+  }
+
+  /// Interpreter whose randomness is drawn from the seed, the same seed and answers replay the same game.
+  pub fn with_seed(fsm: FSM, seed: u64) -> Self {
+    let state = GameState {
+      rng: Rng::new(seed),
+      ..GameState::default()
+    };
+
+    Interpreter {
+      current_state: fsm.entry,
+      fsm,
+      logged: None,
+      state,
+      log: vec![Event::Seed(seed)],
+      history: History::default(),
+    }
+  }
+
+  /// Interpreter that continues a game in the given state, the log ends with the state.
+  /// Diffs are recorded on if the log has them.
+  pub(crate) fn resume(fsm: FSM, current_state: StateID, state: GameState, log: Vec<Event>) -> Self {
+    let diffs = log.iter().any(|event| matches!(event, Event::Diff(_)));
+
+    Interpreter {
+      fsm,
+      current_state,
+      logged: diffs.then(|| state.clone()),
+      state,
+      log,
+      history: History::default(),
+    }
+  }
+
+  /// Records a Diff of the game state after every transition, so the log alone rebuilds the state.
+  /// Off by default, every Diff compares and copies the whole state.
+  pub fn with_diffs(mut self) -> Self {
+    self.logged = Some(self.state.clone());
+    self
+  }
+
+  /// Builds the FSM of the game and returns an Interpreter for it, seeded with 0.
+  pub fn from_game(game: Game) -> Result<Self, RuntimeError> {
+    Interpreter::from_game_with_seed(game, 0)
+  }
+
+  /// Builds the FSM of the game and returns an Interpreter for it with the seed.
+  pub fn from_game_with_seed(game: Game, seed: u64) -> Result<Self, RuntimeError> {
+    Ok(Interpreter::with_seed(build_fsm(game)?, seed))
+  }
+
+  /// The seed all randomness of the game is drawn from.
+  pub fn seed(&self) -> u64 {
+    self.state.rng.seed()
+  }
+
+  pub fn fsm(&self) -> &FSM {
+    &self.fsm
+  }
+
+  pub fn current_state(&self) -> StateID {
+    self.current_state
+  }
+
+  /// Everything that happened so far: the seed, every transition, answer, undo and redo,
+  /// and the state changes if Diffs are recorded (`with_diffs`). Events are only ever appended.
+  pub fn log(&self) -> &[Event] {
+    &self.log
+  }
+
+  /// Takes the next enabled transition of the current state.
+  /// Returns the pending Input instead if a player has to decide first.
+  pub fn step(&mut self) -> Result<Step, RuntimeError> {
+    if self.state.game_over {
+      return Ok(Step::Finished)
+    }
+
+    // a hand-built FSM may end at a state without transitions that is not one of its goals
+    if self.fsm.is_goal(self.current_state) || self.edges(self.current_state)?.is_empty() {
+      self.state.game_over = true;
+      self.log_diff();
+
+      return Ok(Step::Finished)
+    }
+
+    if let Some(request) = self.pending_input()? {
+      return Ok(Step::Input(request))
+    }
+
+    let (transition_id, to_state) = self.enabled_transition()?;
+    self.take(transition_id, to_state, None)?;
+
+    Ok(Step::Transition(transition_id))
+  }
+
+  /// The input the game is waiting for, None if the next transition can be taken without one.
+  pub fn pending_input(&self) -> Result<Option<InputRequest>, RuntimeError> {
+    let edges = self.edges(self.current_state)?;
+    if self.state.game_over || edges.is_empty() {
+      return Ok(None)
+    }
+
+    let mut choices = Vec::new();
+    let mut optionals = Vec::new();
+    for (transition_id, _) in edges.iter() {
+      match self.transition(*transition_id)? {
+        Transition::Choice => choices.push(*transition_id),
+        Transition::Optional => optionals.push(*transition_id),
+        _ => {},
+      }
+    }
+
+    // the first optional edge enters the body, the second one skips it
+    let options = match optionals.as_slice() {
+      [enter, skip] if choices.is_empty() => Some(Options::Optional { enter: *enter, skip: *skip }),
+      [] if choices.is_empty() => None,
+      _ => Some(Options::Choice(choices.into_iter().chain(optionals).collect())),
+    };
+    if let Some(options) = options {
+      return Ok(Some(
+        InputRequest {
+          player: self.state.current_player()?.to_string(),
+          options,
+        }
+      ))
+    }
+
+    let (transition_id, _) = self.enabled_transition()?;
+    match self.transition(transition_id)? {
+      Transition::Action(rule) => self.state.input_request(rule),
+      _ => Ok(None),
+    }
+  }
+
+  /// Answers the pending Input and takes the transition that was waiting for it.
+  pub fn answer(&mut self, answer: Answer) -> Result<(), RuntimeError> {
+    let request = self.pending_input()?.ok_or(RuntimeError::NoPendingInput)?;
+    if request.answer_type() != answer.answer_type() {
+      return Err(RuntimeError::InvalidAnswer)
+    }
+
+    let decision = self.decision_point(&request.player, &answer);
+
+    // the answer is logged before the transition it takes,
+    // a rejected answer changes nothing and is not part of the game
+    let answered = self.log.len();
+    self.log.push(Event::Answer { player: request.player.clone(), answer: answer.clone() });
+    if let Err(error) = self.take_answer(&request, answer) {
+      self.log.truncate(answered);
+      return Err(error)
+    }
+    self.record_decision(decision);
+
+    Ok(())
+  }
+
+  fn take_answer(&mut self, request: &InputRequest, answer: Answer) -> Result<(), RuntimeError> {
+    if let Answer::Transition(transition_id) = answer {
+      if !request.accepts(&answer) {
+        return Err(RuntimeError::InvalidDecision(transition_id))
+      }
+
+      let to_state = self.edges(self.current_state)?
+        .into_iter()
+        .find(|(id, _)| *id == transition_id)
+        .map(|(_, to_state)| to_state)
+        .ok_or(RuntimeError::InvalidDecision(transition_id))?;
+
+      return self.take(transition_id, to_state, None)
+    }
+
+    let (transition_id, to_state) = self.enabled_transition()?;
+    self.take(transition_id, to_state, Some(&answer))
+  }
+
+  /// Steps until an Input is needed or the game is over.
+  pub fn run(&mut self) -> Result<Step, RuntimeError> {
+    loop {
+      match self.step()? {
+        Step::Transition(_) => continue,
+        step => return Ok(step),
+      }
+    }
+  }
+
+  fn edges(&self, state: StateID) -> Result<Vec<(TransitionID, StateID)>, RuntimeError> {
+    self.fsm.states
+      .get(&state)
+      .cloned()
+      .ok_or(RuntimeError::UnknownState(state))
+  }
+
+  fn transition(&self, transition_id: TransitionID) -> Result<&Transition, RuntimeError> {
+    self.fsm.transitions
+      .get(&transition_id)
+      .ok_or(RuntimeError::UnknownTransition(transition_id))
+  }
+
+  /// The only transition of the current state whose guard holds.
+  fn enabled_transition(&self) -> Result<(TransitionID, StateID), RuntimeError> {
+    let mut enabled = Vec::new();
+    for (transition_id, to_state) in self.edges(self.current_state)?.into_iter() {
+      if self.is_enabled(transition_id)? {
+        enabled.push((transition_id, to_state));
+      }
+    }
+
+    match enabled.as_slice() {
+      [] => Err(RuntimeError::NoTransition(self.current_state)),
+      [transition] => Ok(*transition),
+      _ => Err(RuntimeError::AmbiguousTransition(self.current_state)),
+    }
+  }
+
+  /// Checks if the guard of a transition holds.
+  fn is_enabled(&self, transition_id: TransitionID) -> Result<bool, RuntimeError> {
+    match self.transition(transition_id)?.clone() {
+      Transition::Condition(bool_expr) => self.state.eval_bool(&bool_expr),
+      Transition::NotCondition(bool_expr) => Ok(!self.state.eval_bool(&bool_expr)?),
+      Transition::EndCondition(end_condition) => self.state.eval_end_condition(&end_condition),
+      Transition::NotEndCondition(end_condition) => Ok(!self.state.eval_end_condition(&end_condition)?),
+      Transition::Action(_)
+        | Transition::StageCounter
+        | Transition::EnterStage(_)
+        | Transition::ExitStage(_) => Ok(true),
+      Transition::Choice | Transition::Optional => Ok(false),
+    }
+  }
+
+  /// Applies the transition and moves to the next state.
+  fn take(&mut self, transition_id: TransitionID, to_state: StateID, answer: Option<&Answer>) -> Result<(), RuntimeError> {
+    match self.transition(transition_id)?.clone() {
+      Transition::Action(rule) => match answer {
+        Some(answer) => self.state.execute_answer(&rule, answer)?,
+        None => self.state.execute(&rule)?,
+      },
+      Transition::EnterStage(stage) => {
+        self.state.stages.push(StageFrame::new(stage, to_state));
+
+        // the stage is played for its player, a stage for current keeps the current player
+        let player = self.fsm.stages.get(&stage).map(|info| info.player.clone());
+        if let Some(player) = player.filter(|player| *player != PlayerExpr::Current) {
+          let player = self.state.eval_player(&player)?;
+          self.state.current = self.state.turnorder_index(&player)?;
+        }
+        self.state.start_turn()?;
+      },
+      Transition::ExitStage(_) => {
+        self.state.stages.pop();
+      },
+      Transition::StageCounter => {
+        // a round that did not pass the turn itself ends the turn of its player
+        self.state.pass_turn()?;
+        self.state.stage_mut()?.rounds += 1;
+        self.state.start_turn()?;
+      },
+      _ => {},
+    }
+
+    self.log.push(
+      Event::Transition {
+        from: self.current_state,
+        transition: transition_id,
+        to: to_state,
+      }
+    );
+    self.current_state = to_state;
+    self.log_diff();
+
+    Ok(())
+  }
+
+  /// Logs what changed since the last Diff, if Diffs are recorded.
+  pub(crate) fn log_diff(&mut self) {
+    if let Some(logged) = self.logged.as_mut() {
+      self.log.push(Event::Diff(diff(logged, &self.state)));
+      *logged = self.state.clone();
+    }
+  }
+}
+
+/// Builds the FSM of a game, the errors of the builder become a RuntimeError.
+pub fn build_fsm(game: Game) -> Result<FSM, RuntimeError> {
+  FSMBuilder::default()
+    .build_fsm(game)
+    .map_err(RuntimeError::InvalidGame)
+}
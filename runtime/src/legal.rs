@@ -0,0 +1,212 @@
+use crate::error::RuntimeError;
+use crate::input::{Answer, CardChoice, Options};
+use crate::interpreter::Interpreter;
+use crate::rng::Rng;
+
+/// Open bid ranges (`bid any`, `range(> n)`) have no upper bound, only this many bids are listed.
+pub const MAX_OPEN_BIDS: i32 = 100;
+
+impl Options {
+  /// Every concrete answer the options accept, produced one by one.
+  /// Cards are listed as combinations in the order of the offered cards,
+  /// hidden cards by their index so that no answer reveals their ids.
+  ///
+  /// The number of card combinations grows exponentially with the offered cards
+  /// (`move any` of a full deck has 2^52 answers), do not collect them without checking `count` first.
+  pub fn answers(&self) -> Box<dyn Iterator<Item = Answer> + '_> {
+    match self {
+      Options::Choice(transitions) => Box::new(transitions.iter().copied().map(Answer::Transition)),
+      Options::Optional { enter, skip } => Box::new([Answer::Transition(*enter), Answer::Transition(*skip)].into_iter()),
+      Options::Cards { cards, counts } => {
+        Box::new(
+          counts
+            .iter()
+            .flat_map(|count| Combinations::new(cards, *count))
+            .map(Answer::Cards)
+        )
+      },
+      Options::Amount(amounts) => Box::new(amounts.iter().copied().map(Answer::Amount)),
+      Options::Bid(range) => {
+        Box::new(
+          (range.min..=self.max_bid().unwrap_or(range.min))
+            .filter(|bid| range.contains(*bid))
+            .map(Answer::Int)
+        )
+      },
+      Options::Card(cards) => Box::new(cards.iter().copied().map(Answer::Card)),
+      Options::String(strings) => Box::new(strings.iter().cloned().map(Answer::String)),
+      Options::Int { min, max } => Box::new((*min..=*max).map(Answer::Int)),
+    }
+  }
+
+  /// Whether no answer is accepted, without listing them.
+  pub fn is_empty(&self) -> bool {
+    match self {
+      Options::Cards { cards, counts } => counts.iter().all(|count| *count > cards.len()),
+      Options::Bid(range) => range.is_empty(),
+      Options::Int { min, max } => max < min,
+      _ => self.count() == 0,
+    }
+  }
+
+  /// The number of answers `answers` produces, without listing them.
+  /// Saturates at usize::MAX.
+  pub fn count(&self) -> usize {
+    match self {
+      Options::Choice(transitions) => transitions.len(),
+      Options::Optional { .. } => 2,
+      Options::Cards { cards, counts } => {
+        counts
+          .iter()
+          .fold(0, |total: usize, count| total.saturating_add(binomial(cards.len(), *count)))
+      },
+      Options::Amount(amounts) => amounts.len(),
+      Options::Bid(range) => {
+        match self.max_bid() {
+          Some(max) if max >= range.min => {
+            let bids = (max as i64 - range.min as i64) as usize + 1;
+            match range.except {
+              Some(except) if except >= range.min && except <= max => bids - 1,
+              _ => bids,
+            }
+          },
+          _ => 0,
+        }
+      },
+      Options::Card(cards) => cards.len(),
+      Options::String(strings) => strings.len(),
+      Options::Int { min, max } => {
+        if max < min {
+          0
+        } else {
+          (*max as i64 - *min as i64) as usize + 1
+        }
+      },
+    }
+  }
+
+  /// One random answer, None if there is none.
+  /// Cards are drawn without listing the combinations: first one of the possible counts, then a subset of that size.
+  /// The subset consists of the offered choices, hidden cards stay hidden.
+  pub fn sample(&self, rng: &mut Rng) -> Option<Answer> {
+    match self {
+      Options::Cards { cards, counts } => {
+        let counts = counts
+          .iter()
+          .filter(|count| **count <= cards.len())
+          .collect::<Vec<_>>();
+        if counts.is_empty() {
+          return None
+        }
+        let count = *counts[rng.below(counts.len())];
+
+        let mut indices = (0..cards.len()).collect::<Vec<_>>();
+        for i in 0..count {
+          let j = i + rng.below(cards.len() - i);
+          indices.swap(i, j);
+        }
+        let mut chosen = indices[..count].to_vec();
+        chosen.sort();
+
+        Some(Answer::Cards(chosen.into_iter().map(|index| cards[index]).collect()))
+      },
+      _ => {
+        let count = self.count();
+        if count == 0 {
+          return None
+        }
+
+        self.answers().nth(rng.below(count))
+      },
+    }
+  }
+
+  /// The highest bid that is listed, None if the range is empty.
+  fn max_bid(&self) -> Option<i32> {
+    match self {
+      Options::Bid(range) if !range.is_empty() => {
+        Some(range.max.unwrap_or(range.min.saturating_add(MAX_OPEN_BIDS - 1)))
+      },
+      _ => None,
+    }
+  }
+}
+
+impl Interpreter {
+  /// Every concrete action the asked player can take in the current state.
+  /// Empty if nothing has to be decided and the game goes on with `step`.
+  ///
+  /// Lists all answers at once, which is exponential in the offered cards of a free Quantity,
+  /// use `Options::count` or `Options::sample` of `pending_input` for large requests.
+  pub fn legal_actions(&self) -> Result<Vec<Answer>, RuntimeError> {
+    Ok(
+      self.pending_input()?
+        .map(|request| request.options.answers().collect())
+        .unwrap_or_default()
+    )
+  }
+}
+
+/// Number of subsets with `count` of `n` elements, saturating at usize::MAX.
+fn binomial(n: usize, count: usize) -> usize {
+  if count > n {
+    return 0
+  }
+
+  let count = count.min(n - count);
+  let mut result: u128 = 1;
+  for i in 0..count {
+    // Exact after every step: result is the binomial of (n - count + i + 1, i + 1).
+    result = result * (n - count + i + 1) as u128 / (i + 1) as u128;
+    if result > usize::MAX as u128 {
+      return usize::MAX
+    }
+  }
+
+  result as usize
+}
+
+/// All subsets of the cards with `count` elements, keeping the order of the cards.
+/// Produced lazily in lexicographic order of the card indices.
+struct Combinations<'a> {
+  cards: &'a [CardChoice],
+  indices: Vec<usize>,
+  done: bool,
+}
+
+impl<'a> Combinations<'a> {
+  fn new(cards: &'a [CardChoice], count: usize) -> Self {
+    Combinations {
+      cards,
+      indices: (0..count).collect(),
+      done: count > cards.len(),
+    }
+  }
+}
+
+impl Iterator for Combinations<'_> {
+  type Item = Vec<CardChoice>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    if self.done {
+      return None
+    }
+
+    let subset = self.indices.iter().map(|index| self.cards[*index]).collect();
+
+    // Advance the rightmost index that can still move, the ones after it follow directly.
+    let n = self.cards.len();
+    let k = self.indices.len();
+    match (0..k).rev().find(|i| self.indices[*i] != i + n - k) {
+      Some(i) => {
+        self.indices[i] += 1;
+        for j in i + 1..k {
+          self.indices[j] = self.indices[j - 1] + 1;
+        }
+      },
+      None => self.done = true,
+    }
+
+    Some(subset)
+  }
+}
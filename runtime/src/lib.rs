@@ -0,0 +1,23 @@
+pub mod error;
+pub mod rng;
+pub mod state;
+pub mod turnorder;
+pub mod points;
+pub mod precedence;
+pub mod combo;
+pub mod eval;
+pub mod exec;
+pub mod moves;
+pub mod tokens;
+pub mod memory;
+pub mod scoring;
+pub mod bidding;
+pub mod input;
+pub mod legal;
+pub mod view;
+pub mod replay;
+pub mod save;
+pub mod undo;
+pub mod watchdog;
+pub mod simulate;
+pub mod interpreter;
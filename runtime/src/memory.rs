@@ -0,0 +1,204 @@
+use std::fmt;
+
+use ast::ast::*;
+use serde::{Deserialize, Serialize};
+
+use crate::error::RuntimeError;
+use crate::state::{CardId, GameState, Owner};
+
+/// Type of a memory, fixed by the rule that creates it or by the first Set* rule.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum MemoryType {
+  Int,
+  String,
+  Collection,
+}
+
+impl fmt::Display for MemoryType {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      MemoryType::Int => write!(f, "int"),
+      MemoryType::String => write!(f, "string"),
+      MemoryType::Collection => write!(f, "collection"),
+    }
+  }
+}
+
+/// An evaluated Collection.
+#[derive(Debug, Clone, PartialEq, Hash, Serialize, Deserialize)]
+pub enum CollectionValue {
+  Ints(Vec<i32>),
+  Strings(Vec<String>),
+  Locations(Vec<String>),
+  Players(Vec<String>),
+  Teams(Vec<String>),
+  Cards(Vec<CardId>),
+}
+
+#[derive(Debug, Clone, PartialEq, Hash, Serialize, Deserialize)]
+pub enum MemoryValue {
+  Int(i32),
+  String(String),
+  Collection(CollectionValue),
+}
+
+impl MemoryValue {
+  pub fn memory_type(&self) -> MemoryType {
+    match self {
+      MemoryValue::Int(_) => MemoryType::Int,
+      MemoryValue::String(_) => MemoryType::String,
+      MemoryValue::Collection(_) => MemoryType::Collection,
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Hash, Serialize, Deserialize)]
+pub struct MemoryState {
+  pub name: String,
+  /// The table or a player.
+  pub owner: Owner,
+  /// None until the memory is set for the first time.
+  pub memory_type: Option<MemoryType>,
+  pub value: Option<MemoryValue>,
+}
+
+impl GameState {
+  pub fn memory_index(&self, name: &str, owner: &Owner) -> Option<usize> {
+    self.memories
+      .iter()
+      .position(|memory| memory.name == name && memory.owner == *owner)
+  }
+
+  /// Finds the memory of the player, without a player the table memory and then the one of the current player.
+  pub fn resolve_memory(&self, name: &str, player: Option<&str>) -> Result<usize, RuntimeError> {
+    let player = match player {
+      Some(player) => player,
+      None => {
+        if let Some(index) = self.memory_index(name, &Owner::Table) {
+          return Ok(index)
+        }
+
+        self.current_player()?
+      },
+    };
+
+    self.memory_index(name, &Owner::Player(player.to_string()))
+      .ok_or_else(|| RuntimeError::UnknownMemory(name.to_string()))
+  }
+
+  pub fn memory(&self, name: &str, player: Option<&str>) -> Result<&MemoryState, RuntimeError> {
+    Ok(&self.memories[self.resolve_memory(name, player)?])
+  }
+
+  /// The int value of a memory, fails if the memory was never set or is not an int.
+  pub fn memory_int(&self, name: &str, player: Option<&str>) -> Result<i32, RuntimeError> {
+    match &self.memory(name, player)?.value {
+      Some(MemoryValue::Int(int)) => Ok(*int),
+      Some(value) => Err(
+        RuntimeError::MemoryTypeMismatch {
+          memory: name.to_string(),
+          expected: MemoryType::Int,
+          found: value.memory_type(),
+        }
+      ),
+      None => Err(RuntimeError::UnsetMemory(name.to_string())),
+    }
+  }
+
+  pub fn create_memory(&mut self, name: &str, owner: Owner, value: Option<MemoryValue>) -> Result<(), RuntimeError> {
+    if self.memory_index(name, &owner).is_some() {
+      return Err(RuntimeError::DuplicateMemory(name.to_string()))
+    }
+
+    self.memories.push(
+      MemoryState {
+        name: name.to_string(),
+        owner,
+        memory_type: value.as_ref().map(MemoryValue::memory_type),
+        value,
+      }
+    );
+
+    Ok(())
+  }
+
+  /// Sets the memory of the table or the current player, the value has to match the type of the memory.
+  pub fn set_memory(&mut self, name: &str, value: MemoryValue) -> Result<(), RuntimeError> {
+    let index = self.resolve_memory(name, None)?;
+    let memory = &mut self.memories[index];
+
+    match memory.memory_type {
+      Some(memory_type) if memory_type != value.memory_type() => Err(
+        RuntimeError::MemoryTypeMismatch {
+          memory: name.to_string(),
+          expected: memory_type,
+          found: value.memory_type(),
+        }
+      ),
+      _ => {
+        memory.memory_type = Some(value.memory_type());
+        memory.value = Some(value);
+
+        Ok(())
+      },
+    }
+  }
+
+  pub fn eval_collection(&self, collection: &Collection) -> Result<CollectionValue, RuntimeError> {
+    match collection {
+      Collection::IntCollection(ints) => Ok(CollectionValue::Ints(self.eval_ints(ints)?)),
+      Collection::StringCollection(strings) => {
+        let strings = strings.strings
+          .iter()
+          .map(|string| self.eval_string(string))
+          .collect::<Result<_, _>>()?;
+
+        Ok(CollectionValue::Strings(strings))
+      },
+      Collection::LocationCollection(locations) => {
+        Ok(CollectionValue::Locations(locations.locations.iter().map(|location| location.to_string()).collect()))
+      },
+      Collection::PlayerCollection(players) => Ok(CollectionValue::Players(self.eval_players(players)?)),
+      Collection::TeamCollection(teams) => Ok(CollectionValue::Teams(self.eval_teams(teams)?)),
+      Collection::CardSet(cardset) => Ok(CollectionValue::Cards(self.eval_cardset(cardset)?)),
+    }
+  }
+
+  /// The players that own the int memory with their values, in turnorder.
+  /// Players whose memory was never set are left out.
+  pub fn memory_values(&self, name: &str) -> Result<Vec<(String, i32)>, RuntimeError> {
+    let mut values = Vec::new();
+    let mut known = false;
+    for player in self.turnorder.iter() {
+      let Some(index) = self.memory_index(name, &Owner::Player(player.clone())) else {
+        continue
+      };
+      known = true;
+
+      if self.memories[index].value.is_some() {
+        values.push((player.clone(), self.memory_int(name, Some(player))?));
+      }
+    }
+
+    if !known {
+      return Err(RuntimeError::UnknownMemory(name.to_string()))
+    }
+
+    Ok(values)
+  }
+
+  /// The player with the highest (or lowest) value of the memory, the first one in turnorder wins ties.
+  pub fn owner_of_extreme(&self, name: &str, highest: bool) -> Result<String, RuntimeError> {
+    let values = self.memory_values(name)?;
+
+    let owner = if highest {
+      values.iter().rev().max_by_key(|(_, value)| *value)
+    } else {
+      values.iter().min_by_key(|(_, value)| *value)
+    };
+
+    owner
+      .map(|(player, _)| player.clone())
+      .ok_or(RuntimeError::EmptyCollection)
+  }
+}
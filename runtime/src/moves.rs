@@ -0,0 +1,280 @@
+use ast::ast::*;
+
+use crate::error::RuntimeError;
+use crate::input::{Answer, CardChoice, Options};
+use crate::state::{CardId, CardStatus, GameState};
+
+impl GameState {
+  /// Returns the options the acting player has for the free Quantity of a move.
+  /// Moves with a fixed Quantity (or a single allowed count) return None.
+  /// Cards the current player can not see are offered as `CardChoice::Hidden`.
+  pub fn move_options(&self, rule: &Rule) -> Result<Option<Options>, RuntimeError> {
+    let options = match rule {
+      Rule::ClassicMove(ClassicMove::MoveQuantity(quantity, from, _, _)) => {
+        let cards = self.eval_cardset(from)?;
+        let counts = self.counts(quantity, cards.len())?;
+
+        Options::Cards { cards: self.choices(&cards)?, counts }
+      },
+      Rule::ExchangeMove(ExchangeMove::ExchangeQuantity(quantity, from, _, with)) => {
+        let cards = self.eval_cardset(from)?;
+        let available = cards.len().min(self.eval_cardset(with)?.len());
+        let counts = self.counts(quantity, available)?;
+
+        Options::Cards { cards: self.choices(&cards)?, counts }
+      },
+      Rule::DealMove(DealMove::DealQuantity(quantity, from, _, to)) => {
+        if let Quantity::Quantifier(Quantifier::All) = quantity {
+          return Ok(None)
+        }
+
+        let available = self.eval_cardset(from)?.len() / self.target_locations(to)?.len().max(1);
+
+        Options::Amount(self.counts(quantity, available)?)
+      },
+      Rule::TokenMove(TokenMove::PlaceQuantity(quantity, from, to)) => {
+        Options::Amount(self.token_amounts(quantity, from, to)?)
+      },
+      _ => return Ok(None),
+    };
+
+    match &options {
+      Options::Cards { counts, .. } | Options::Amount(counts) if counts.len() < 2 => Ok(None),
+      _ => Ok(Some(options)),
+    }
+  }
+
+  /// Numbers of cards the Quantity allows if `available` cards can be moved, in ascending order.
+  /// `any` means at least one card, ranges may be satisfied by zero cards.
+  /// A Quantity that allows no count at all is invalid.
+  pub fn counts(&self, quantity: &Quantity, available: usize) -> Result<Vec<usize>, RuntimeError> {
+    let available = available as i64;
+
+    let (min, max, except) = match quantity {
+      Quantity::Int(int) => {
+        let int = self.eval_int(int)? as i64;
+
+        (int, int, None)
+      },
+      Quantity::Quantifier(Quantifier::All) => (available, available, None),
+      Quantity::Quantifier(Quantifier::Any) => (1, available, None),
+      Quantity::IntRange(range) => match range {
+        IntRange::Eq(int) => {
+          let int = self.eval_int(int)? as i64;
+
+          (int, int, None)
+        },
+        IntRange::Neq(int) => (0, available, Some(self.eval_int(int)? as i64)),
+        IntRange::Gt(int) => (self.eval_int(int)? as i64 + 1, available, None),
+        IntRange::Ge(int) => (self.eval_int(int)? as i64, available, None),
+        IntRange::Lt(int) => (0, self.eval_int(int)? as i64 - 1, None),
+        IntRange::Le(int) => (0, self.eval_int(int)? as i64, None),
+      },
+    };
+
+    if max < min.max(0) {
+      return Err(RuntimeError::InvalidQuantity)
+    }
+
+    let min = min.max(0);
+    let counts = (min..=max.min(available))
+      .filter(|count| Some(*count) != except)
+      .map(|count| count as usize)
+      .collect::<Vec<_>>();
+
+    if counts.is_empty() {
+      return Err(
+        RuntimeError::NotEnoughCards {
+          needed: if except == Some(min) { min + 1 } else { min } as usize,
+          available: available as usize,
+        }
+      )
+    }
+
+    Ok(counts)
+  }
+
+  /// Executes a ClassicMove, DealMove, ExchangeMove or TokenMove.
+  /// A free Quantity needs the answer of the acting player.
+  pub fn execute_move(&mut self, rule: &Rule, answer: Option<&Answer>) -> Result<(), RuntimeError> {
+    match rule {
+      Rule::ClassicMove(ClassicMove::Move(from, status, to)) => {
+        let cards = self.eval_cardset(from)?;
+        let to = self.target_location(to)?;
+
+        self.move_cards(&cards, status.into(), to)
+      },
+      Rule::ClassicMove(ClassicMove::MoveQuantity(quantity, from, status, to)) => {
+        let cards = self.eval_cardset(from)?;
+        let counts = self.counts(quantity, cards.len())?;
+        let cards = self.pick(cards, &counts, answer)?;
+        let to = self.target_location(to)?;
+
+        self.move_cards(&cards, status.into(), to)
+      },
+      Rule::DealMove(DealMove::Deal(from, status, to)) => {
+        let cards = self.eval_cardset(from)?;
+
+        self.deal(cards, status.into(), to)
+      },
+      Rule::DealMove(DealMove::DealQuantity(quantity, from, status, to)) => {
+        let mut cards = self.eval_cardset(from)?;
+
+        if !matches!(quantity, Quantity::Quantifier(Quantifier::All)) {
+          let targets = self.target_locations(to)?.len();
+          let amounts = self.counts(quantity, cards.len() / targets.max(1))?;
+          let amount = match (amounts.as_slice(), answer) {
+            ([amount], _) => *amount,
+            (_, Some(Answer::Amount(amount))) if amounts.contains(amount) => *amount,
+            (_, None) => return Err(RuntimeError::UndecidedQuantity),
+            _ => return Err(RuntimeError::InvalidQuantity),
+          };
+          cards.truncate(amount * targets);
+        }
+
+        self.deal(cards, status.into(), to)
+      },
+      Rule::ExchangeMove(ExchangeMove::Exchange(from, status, with)) => {
+        let cards = self.eval_cardset(from)?;
+        let others = self.eval_cardset(with)?;
+
+        self.exchange(&cards, &others, status.into(), from, with)
+      },
+      Rule::ExchangeMove(ExchangeMove::ExchangeQuantity(quantity, from, status, with)) => {
+        let cards = self.eval_cardset(from)?;
+        let mut others = self.eval_cardset(with)?;
+        let counts = self.counts(quantity, cards.len().min(others.len()))?;
+        let cards = self.pick(cards, &counts, answer)?;
+        others.truncate(cards.len());
+
+        self.exchange(&cards, &others, status.into(), from, with)
+      },
+      Rule::TokenMove(token_move) => {
+        self.place_tokens(token_move, answer)
+      },
+      _ => Err(RuntimeError::Unsupported(format!("{:?}", rule))),
+    }
+  }
+
+  /// Takes the top cards if the count is fixed, otherwise the cards of the answer.
+  fn pick(&self, cards: Vec<CardId>, counts: &[usize], answer: Option<&Answer>) -> Result<Vec<CardId>, RuntimeError> {
+    if let [count] = counts {
+      return Ok(cards[..*count].to_vec())
+    }
+
+    let options = Options::Cards { cards: self.choices(&cards)?, counts: counts.to_vec() };
+    match answer {
+      Some(answer @ Answer::Cards(picked)) if options.accepts(answer) => {
+        Ok(
+          picked
+            .iter()
+            .map(|choice| match choice {
+              CardChoice::Card(card) => *card,
+              CardChoice::Hidden(index) => cards[*index],
+            })
+            .collect()
+        )
+      },
+      None => Err(RuntimeError::UndecidedQuantity),
+      _ => Err(RuntimeError::InvalidQuantity),
+    }
+  }
+
+  /// Deals the cards one by one to the target locations, starting with the first one.
+  fn deal(&mut self, cards: Vec<CardId>, status: CardStatus, to: &CardSet) -> Result<(), RuntimeError> {
+    let targets = self.target_locations(to)?;
+    if targets.is_empty() {
+      return Err(RuntimeError::EmptyCollection)
+    }
+
+    for (index, card) in cards.into_iter().enumerate() {
+      self.move_cards(&[card], status, targets[index % targets.len()])?;
+    }
+
+    Ok(())
+  }
+
+  fn exchange(
+    &mut self,
+    cards: &[CardId],
+    others: &[CardId],
+    status: CardStatus,
+    from: &CardSet,
+    with: &CardSet
+  ) -> Result<(), RuntimeError> {
+    let from = self.target_location(from)?;
+    let with = self.target_location(with)?;
+
+    self.move_cards(cards, status, with)?;
+    self.move_cards(others, status, from)
+  }
+
+  /// Puts the cards on top of the location, keeping their order.
+  fn move_cards(&mut self, cards: &[CardId], status: CardStatus, to: usize) -> Result<(), RuntimeError> {
+    for card in cards.iter() {
+      let from = self.location_of(*card)?;
+      self.locations[from].cards.retain(|id| id != card);
+      self.cards[*card].status = status;
+    }
+
+    self.locations[to].cards.splice(0..0, cards.iter().copied());
+
+    Ok(())
+  }
+
+  /// The single location a CardSet refers to.
+  fn target_location(&self, cardset: &CardSet) -> Result<usize, RuntimeError> {
+    match self.target_locations(cardset)?.as_slice() {
+      [location] => Ok(*location),
+      _ => Err(RuntimeError::AmbiguousLocation(format!("{:?}", cardset))),
+    }
+  }
+
+  /// The locations a CardSet refers to, one per player of a GroupOfPlayerCollection.
+  pub fn target_locations(&self, cardset: &CardSet) -> Result<Vec<usize>, RuntimeError> {
+    match cardset {
+      CardSet::Group(group) => {
+        self.group_locations(group, None)
+      },
+      CardSet::GroupOfPlayer(group, player) => {
+        let player = self.eval_player(player)?;
+
+        self.group_locations(group, Some(&player))
+      },
+      CardSet::GroupOfPlayerCollection(group, players) => {
+        let mut locations = Vec::new();
+        for player in self.eval_players(players)? {
+          locations.extend(self.group_locations(group, Some(&player))?);
+        }
+
+        Ok(locations)
+      },
+    }
+  }
+
+  fn group_locations(&self, group: &Group, player: Option<&str>) -> Result<Vec<usize>, RuntimeError> {
+    let location = |location: &Location| self.resolve_location(&location.to_string(), player);
+
+    match group {
+      Group::Location(loc)
+        | Group::LocationWhere(loc, _)
+        | Group::ComboInLocation(_, loc)
+        | Group::NotComboInLocation(_, loc) => Ok(vec![location(loc)?]),
+      Group::LocationCollection(locs)
+        | Group::LocationCollectionWhere(locs, _)
+        | Group::ComboInLocationCollection(_, locs)
+        | Group::NotComboInLocationCollection(_, locs) => {
+        locs.locations.iter().map(location).collect()
+      },
+      Group::CardPosition(card_position) => match card_position {
+        CardPosition::At(loc, _) | CardPosition::Top(loc) | CardPosition::Bottom(loc) => Ok(vec![location(loc)?]),
+        _ => {
+          let card = self.eval_card_position(card_position, player)?
+            .ok_or_else(|| RuntimeError::NoCard(format!("{:?}", card_position)))?;
+
+          Ok(vec![self.location_of(card)?])
+        },
+      },
+    }
+  }
+}
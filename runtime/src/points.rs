@@ -0,0 +1,23 @@
+use serde::{Deserialize, Serialize};
+
+use crate::state::Card;
+
+/// A PointMap resolved to concrete points.
+#[derive(Debug, Clone, PartialEq, Hash, Serialize, Deserialize)]
+pub struct PointMapState {
+  pub name: String,
+  /// Points for (Key, Value) pairs.
+  pub points: Vec<(String, String, i32)>,
+}
+
+impl PointMapState {
+  /// Points of the card: the sum of all entries that match the card.
+  /// A card without a matching entry is worth 0 points.
+  pub fn points_of(&self, card: &Card) -> i32 {
+    self.points
+      .iter()
+      .filter(|(key, value, _)| card.value(key) == Some(value.as_str()))
+      .map(|(_, _, points)| points)
+      .sum()
+  }
+}
@@ -0,0 +1,93 @@
+use std::cmp::Ordering;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::RuntimeError;
+use crate::state::{Card, CardId, GameState};
+
+/// A Precedence resolved to an ordering of (Key, Value) pairs, lowest first.
+///
+/// `precedence RankOrder on Rank(Two, ..., Ace)` orders a single key.
+/// The pair form may mix keys: `precedence Trump (Rank(Two), ..., Rank(Ace), Suite(Hearts))`
+/// puts every Hearts card above all other cards and orders by rank afterwards.
+#[derive(Debug, Clone, PartialEq, Hash, Serialize, Deserialize)]
+pub struct PrecedenceState {
+  pub name: String,
+  pub order: Vec<(String, String)>,
+}
+
+impl PrecedenceState {
+  /// Position of the value of the card among the values of the key, None if the card is not ordered by it.
+  pub fn rank_of(&self, card: &Card, key: &str) -> Option<usize> {
+    let value = card.value(key)?;
+
+    self.order
+      .iter()
+      .filter(|(k, _)| k == key)
+      .position(|(_, v)| v == value)
+  }
+
+  /// Number of values of the key in the precedence.
+  pub fn len_of(&self, key: &str) -> usize {
+    self.order.iter().filter(|(k, _)| k == key).count()
+  }
+
+  /// Positions of all pairs that match the card, highest first.
+  fn strength(&self, card: &Card) -> Vec<usize> {
+    let mut positions = self.order
+      .iter()
+      .enumerate()
+      .filter(|(_, (key, value))| card.value(key) == Some(value.as_str()))
+      .map(|(position, _)| position)
+      .collect::<Vec<_>>();
+    positions.sort_by(|a, b| b.cmp(a));
+
+    positions
+  }
+
+  /// Compares two cards: the card with the higher matching pair wins, further pairs break ties.
+  /// Cards without a matching pair are the lowest, cards with the same pairs are equal.
+  pub fn compare(&self, left: &Card, right: &Card) -> Ordering {
+    self.strength(left).cmp(&self.strength(right))
+  }
+}
+
+/// Checks if the ranks form a run without gaps or duplicates.
+/// With wrap_around the highest of the len ranks is followed by the lowest one (Q-K-A-2).
+pub fn is_run(ranks: &[usize], len: usize, wrap_around: bool) -> bool {
+  let mut sorted = ranks.to_vec();
+  sorted.sort();
+  sorted.dedup();
+  if sorted.len() != ranks.len() {
+    return false
+  }
+  if sorted.len() < 2 {
+    return true
+  }
+
+  let gaps = sorted
+    .windows(2)
+    .filter(|pair| pair[0] + 1 != pair[1])
+    .count();
+
+  if !wrap_around {
+    return gaps == 0
+  }
+
+  // on a circle a run leaves exactly one gap (none if it covers every rank)
+  let closes = sorted[0] == 0 && sorted[sorted.len() - 1] + 1 == len;
+
+  gaps + usize::from(!closes) <= 1
+}
+
+impl GameState {
+  /// Compares two cards by the precedence.
+  pub fn compare_cards(&self, left: CardId, right: CardId, precedence: &str) -> Result<Ordering, RuntimeError> {
+    Ok(self.precedence(precedence)?.compare(self.card(left)?, self.card(right)?))
+  }
+
+  /// Points of a card in the point map.
+  pub fn points_of(&self, card: CardId, point_map: &str) -> Result<i32, RuntimeError> {
+    Ok(self.point_map(point_map)?.points_of(self.card(card)?))
+  }
+}
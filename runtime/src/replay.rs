@@ -0,0 +1,263 @@
+use std::fmt;
+
+use ast::ast::*;
+use ir::fsm::{FSM, StateID, TransitionID};
+use serde::{Deserialize, Serialize};
+
+use crate::error::RuntimeError;
+use crate::input::{Answer, Demanded};
+use crate::interpreter::{Interpreter, build_fsm};
+use crate::memory::MemoryState;
+use crate::points::PointMapState;
+use crate::precedence::PrecedenceState;
+use crate::rng::Rng;
+use crate::state::{Card, GameState, LocationState, Player, StageFrame, Team};
+
+/// Entry of the append-only log of a game.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Event {
+  /// First event of every log, the seed all randomness is drawn from.
+  Seed(u64),
+  /// A transition of the FSM was taken.
+  Transition { from: StateID, transition: TransitionID, to: StateID },
+  /// A player answered the pending input.
+  Answer { player: String, answer: Answer },
+  /// The random draws continue from this seed, logged when a decision was undone.
+  Reseed(u64),
+  /// The last decision was undone, the game is back before its answer.
+  Undo,
+  /// The last undone decision was taken again.
+  Redo,
+  /// What changed in the game state since the last Diff.
+  Diff(Vec<Change>),
+}
+
+/// A part of the game state that changed, with its new value.
+/// Locations and cards are logged one by one, everything else as a whole.
+/// Combos are definitions of the Game and not logged.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Change {
+  Players(Vec<Player>),
+  Teams(Vec<Team>),
+  /// A location was created or its cards or tokens changed.
+  Location(usize, LocationState),
+  /// A card was created or its status changed.
+  Card(Card),
+  PointMaps(Vec<PointMapState>),
+  Precedences(Vec<PrecedenceState>),
+  WrapAround(Vec<String>),
+  Memories(Vec<MemoryState>),
+  Bids(Vec<(String, i32)>),
+  Demanded(Option<Demanded>),
+  Turnorder(Vec<String>),
+  Current(usize),
+  Stages(Vec<StageFrame>),
+  Winners(Vec<String>),
+  GameOver(bool),
+  Rng(Rng),
+}
+
+/// Changes that turn the state `before` into `after`.
+pub fn diff(before: &GameState, after: &GameState) -> Vec<Change> {
+  let GameState {
+    players,
+    teams,
+    locations,
+    cards,
+    point_maps,
+    precedences,
+    wrap_around,
+    combos: _,
+    memories,
+    bids,
+    demanded,
+    turnorder,
+    current,
+    stages,
+    winners,
+    game_over,
+    rng,
+  } = after;
+
+  let mut changes = Vec::new();
+  let mut whole = |changed: bool, change: fn(&GameState) -> Change| {
+    if changed {
+      changes.push(change(after));
+    }
+  };
+
+  whole(*players != before.players, |state| Change::Players(state.players.clone()));
+  whole(*teams != before.teams, |state| Change::Teams(state.teams.clone()));
+  whole(*point_maps != before.point_maps, |state| Change::PointMaps(state.point_maps.clone()));
+  whole(*precedences != before.precedences, |state| Change::Precedences(state.precedences.clone()));
+  whole(*wrap_around != before.wrap_around, |state| Change::WrapAround(state.wrap_around.clone()));
+  whole(*memories != before.memories, |state| Change::Memories(state.memories.clone()));
+  whole(*bids != before.bids, |state| Change::Bids(state.bids.clone()));
+  whole(*demanded != before.demanded, |state| Change::Demanded(state.demanded.clone()));
+  whole(*turnorder != before.turnorder, |state| Change::Turnorder(state.turnorder.clone()));
+  whole(*current != before.current, |state| Change::Current(state.current));
+  whole(*stages != before.stages, |state| Change::Stages(state.stages.clone()));
+  whole(*winners != before.winners, |state| Change::Winners(state.winners.clone()));
+  whole(*game_over != before.game_over, |state| Change::GameOver(state.game_over));
+  whole(*rng != before.rng, |state| Change::Rng(state.rng.clone()));
+
+  for (index, location) in locations.iter().enumerate() {
+    if before.locations.get(index) != Some(location) {
+      changes.push(Change::Location(index, location.clone()));
+    }
+  }
+  for card in cards.iter() {
+    if before.cards.get(card.id) != Some(card) {
+      changes.push(Change::Card(card.clone()));
+    }
+  }
+
+  changes
+}
+
+impl GameState {
+  /// Applies the changes of a Diff, replaying every Diff of a log on a new state rebuilds the game.
+  pub fn apply(&mut self, changes: &[Change]) {
+    for change in changes.iter().cloned() {
+      match change {
+        Change::Players(players) => self.players = players,
+        Change::Teams(teams) => self.teams = teams,
+        Change::Location(index, location) => {
+          if index < self.locations.len() {
+            self.locations[index] = location;
+          } else {
+            self.locations.push(location);
+          }
+        },
+        Change::Card(card) => {
+          if card.id < self.cards.len() {
+            let id = card.id;
+            self.cards[id] = card;
+          } else {
+            self.cards.push(card);
+          }
+        },
+        Change::PointMaps(point_maps) => self.point_maps = point_maps,
+        Change::Precedences(precedences) => self.precedences = precedences,
+        Change::WrapAround(wrap_around) => self.wrap_around = wrap_around,
+        Change::Memories(memories) => self.memories = memories,
+        Change::Bids(bids) => self.bids = bids,
+        Change::Demanded(demanded) => self.demanded = demanded,
+        Change::Turnorder(turnorder) => self.turnorder = turnorder,
+        Change::Current(current) => self.current = current,
+        Change::Stages(stages) => self.stages = stages,
+        Change::Winners(winners) => self.winners = winners,
+        Change::GameOver(game_over) => self.game_over = game_over,
+        Change::Rng(rng) => self.rng = rng,
+      }
+    }
+  }
+}
+
+/// The first event of a log that the replay did not reproduce.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Divergence {
+  /// Index of the event in the log.
+  pub index: usize,
+  pub expected: Event,
+  /// The event of the replay, None if the replay stopped before it.
+  pub found: Option<Event>,
+  /// Why the replay stopped.
+  pub error: Option<RuntimeError>,
+}
+
+impl fmt::Display for Divergence {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "event {} diverged: expected {:?}", self.index, self.expected)?;
+
+    if let Some(found) = &self.found {
+      write!(f, ", found {:?}", found)?;
+    }
+    if let Some(error) = &self.error {
+      write!(f, ", replay failed: {}", error)?;
+    }
+
+    Ok(())
+  }
+}
+
+/// Re-runs a log against the game and checks that every event is reproduced.
+/// Returns the Interpreter at the end of the log, or the first event that diverged.
+pub fn replay(game: Game, log: &[Event]) -> Result<Interpreter, Box<Divergence>> {
+  let seed = match log.first() {
+    Some(Event::Seed(seed)) => *seed,
+    Some(event) => return Err(Box::new(
+      Divergence {
+        index: 0,
+        expected: event.clone(),
+        found: Some(Event::Seed(0)),
+        error: None,
+      }
+    )),
+    None => return Ok(Interpreter::with_seed(build(game, log)?, 0)),
+  };
+
+  let mut interpreter = Interpreter::with_seed(build(game, log)?, seed);
+  if log.iter().any(|event| matches!(event, Event::Diff(_))) {
+    interpreter = interpreter.with_diffs();
+  }
+  let mut checked = 1;
+
+  while checked < log.len() {
+    let result = match &log[checked] {
+      Event::Answer { answer, .. } => interpreter.answer(answer.clone()),
+      Event::Reseed(seed) => {
+        interpreter.reseed(*seed);
+        Ok(())
+      },
+      Event::Undo => interpreter.undo(),
+      Event::Redo => interpreter.redo(),
+      // an Input or Finished step logs nothing, the divergence is reported below
+      _ => interpreter.step().map(|_| ()),
+    };
+
+    let replayed = &interpreter.log()[checked..];
+    if replayed.is_empty() {
+      return Err(Box::new(
+        Divergence {
+          index: checked,
+          expected: log[checked].clone(),
+          found: None,
+          error: result.err(),
+        }
+      ))
+    }
+
+    for event in replayed.iter() {
+      let Some(expected) = log.get(checked) else {
+        break
+      };
+      if event != expected {
+        return Err(Box::new(
+          Divergence {
+            index: checked,
+            expected: expected.clone(),
+            found: Some(event.clone()),
+            error: None,
+          }
+        ))
+      }
+
+      checked += 1;
+    }
+  }
+
+  Ok(interpreter)
+}
+
+/// Builds the FSM of the game, a game without one diverges at the first event.
+fn build(game: Game, log: &[Event]) -> Result<FSM, Box<Divergence>> {
+  build_fsm(game).map_err(|err| Box::new(
+    Divergence {
+      index: 0,
+      expected: log.first().cloned().unwrap_or(Event::Seed(0)),
+      found: None,
+      error: Some(err),
+    }
+  ))
+}
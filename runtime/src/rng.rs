@@ -0,0 +1,49 @@
+use serde::{Deserialize, Serialize};
+
+/// Small deterministic random number generator (SplitMix64).
+/// The same seed always yields the same sequence, on every platform.
+/// All randomness of a game (ShuffleAction, CreateTurnorderRandom) is drawn from it.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub struct Rng {
+  seed: u64,
+  state: u64,
+}
+
+impl Rng {
+  pub fn new(seed: u64) -> Self {
+    Rng { seed, state: seed }
+  }
+
+  /// The seed the generator was created with, enough to replay every draw.
+  pub fn seed(&self) -> u64 {
+    self.seed
+  }
+
+  /// Continues the draws from another seed, `seed()` still returns the seed of the game.
+  pub fn reseed(&mut self, seed: u64) {
+    self.state = seed;
+  }
+
+  pub fn next_u64(&mut self) -> u64 {
+    self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+
+    let mut z = self.state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+
+    z ^ (z >> 31)
+  }
+
+  /// Uniform value in 0..bound, bound has to be greater than 0.
+  pub fn below(&mut self, bound: usize) -> usize {
+    (self.next_u64() % bound as u64) as usize
+  }
+
+  /// Fisher-Yates shuffle.
+  pub fn shuffle<T>(&mut self, items: &mut [T]) {
+    for i in (1..items.len()).rev() {
+      let j = self.below(i + 1);
+      items.swap(i, j);
+    }
+  }
+}
@@ -0,0 +1,85 @@
+use ast::ast::*;
+use ir::fsm::*;
+use serde::{Deserialize, Serialize};
+
+use crate::error::RuntimeError;
+use crate::interpreter::{Interpreter, build_fsm};
+use crate::replay::Event;
+use crate::state::GameState;
+
+/// A running game that can be written to RON, JSON or bincode and resumed later.
+/// The FSM is not part of it, a save is resumed with the Game it was started from.
+/// Neither are the undo and redo stacks, a resumed game starts without decisions to undo.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedGame {
+  pub current_state: StateID,
+  pub state: GameState,
+  /// Names of the created combos, their definitions are taken from the Game.
+  pub combos: Vec<String>,
+  pub log: Vec<Event>,
+}
+
+impl SavedGame {
+  pub fn to_ron(&self) -> Result<String, RuntimeError> {
+    ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+      .map_err(|err| RuntimeError::InvalidSave(err.to_string()))
+  }
+
+  pub fn from_ron(ron: &str) -> Result<Self, RuntimeError> {
+    ron::from_str(ron).map_err(|err| RuntimeError::InvalidSave(err.to_string()))
+  }
+
+  pub fn to_json(&self) -> Result<String, RuntimeError> {
+    serde_json::to_string_pretty(self).map_err(|err| RuntimeError::InvalidSave(err.to_string()))
+  }
+
+  pub fn from_json(json: &str) -> Result<Self, RuntimeError> {
+    serde_json::from_str(json).map_err(|err| RuntimeError::InvalidSave(err.to_string()))
+  }
+
+  pub fn to_bincode(&self) -> Result<Vec<u8>, RuntimeError> {
+    bincode::serialize(self).map_err(|err| RuntimeError::InvalidSave(err.to_string()))
+  }
+
+  pub fn from_bincode(bytes: &[u8]) -> Result<Self, RuntimeError> {
+    bincode::deserialize(bytes).map_err(|err| RuntimeError::InvalidSave(err.to_string()))
+  }
+}
+
+impl Interpreter {
+  /// Saves the game, changes made to `state` outside of a step are saved but not logged.
+  pub fn save(&self) -> SavedGame {
+    let mut state = self.state.clone();
+    let combos = std::mem::take(&mut state.combos);
+
+    SavedGame {
+      current_state: self.current_state(),
+      state,
+      combos: combos.into_iter().map(|(name, _)| name).collect(),
+      log: self.log().to_vec(),
+    }
+  }
+
+  /// Resumes a saved game, the Game has to be the one the save was started from.
+  pub fn load(game: Game, saved: SavedGame) -> Result<Self, RuntimeError> {
+    let fsm = build_fsm(game)?;
+    if !fsm.states.contains_key(&saved.current_state) {
+      return Err(RuntimeError::UnknownState(saved.current_state))
+    }
+
+    let mut state = saved.state;
+    for name in saved.combos.iter() {
+      let filter = fsm.transitions
+        .values()
+        .find_map(|transition| match transition {
+          Transition::Action(Rule::CreateCombo(combo, filter)) if combo == name => Some(filter.clone()),
+          _ => None,
+        })
+        .ok_or_else(|| RuntimeError::UnknownCombo(name.clone()))?;
+
+      state.combos.push((name.clone(), filter));
+    }
+
+    Ok(Interpreter::resume(fsm, saved.current_state, state, saved.log))
+  }
+}
@@ -0,0 +1,233 @@
+use ast::ast::*;
+use serde::{Deserialize, Serialize};
+
+use crate::error::RuntimeError;
+use crate::memory::{MemoryType, MemoryValue};
+use crate::state::GameState;
+
+/// How and when a player left the game.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Finish {
+  /// Order of leaving the game, the first player out has position 1.
+  pub position: usize,
+  /// PlayerOutOfGameSucc (finished) or PlayerOutOfGameFail (eliminated).
+  pub successful: bool,
+}
+
+impl GameState {
+  /// Adds the points to the score of the player.
+  pub fn add_score(&mut self, player: &str, points: i32) -> Result<(), RuntimeError> {
+    let player = self.player_mut(player)?;
+    player.score = player.score.checked_add(points).ok_or(RuntimeError::Overflow)?;
+
+    Ok(())
+  }
+
+  /// Adds the points to the int memory of the player, an unset memory counts as 0.
+  pub fn add_score_memory(&mut self, player: &str, memory: &str, points: i32) -> Result<(), RuntimeError> {
+    let index = self.resolve_memory(memory, Some(player))?;
+    let score = match self.memories[index].value {
+      None => 0,
+      Some(_) => self.memory_int(memory, Some(player))?,
+    };
+    let score = score.checked_add(points).ok_or(RuntimeError::Overflow)?;
+
+    let memory = &mut self.memories[index];
+    memory.memory_type = Some(MemoryType::Int);
+    memory.value = Some(MemoryValue::Int(score));
+
+    Ok(())
+  }
+
+  pub fn execute_score(&mut self, score_rule: &ScoreRule) -> Result<(), RuntimeError> {
+    match score_rule {
+      ScoreRule::ScorePlayer(int, player) => {
+        let points = self.eval_int(int)?;
+        let player = self.eval_player(player)?;
+
+        self.add_score(&player, points)
+      },
+      ScoreRule::ScorePlayerMemory(int, memory, player) => {
+        let points = self.eval_int(int)?;
+        let player = self.eval_player(player)?;
+
+        self.add_score_memory(&player, &memory.to_string(), points)
+      },
+      ScoreRule::ScorePlayerCollection(int, players) => {
+        let points = self.eval_int(int)?;
+        for player in self.eval_players(players)? {
+          self.add_score(&player, points)?;
+        }
+
+        Ok(())
+      },
+      ScoreRule::ScorePlayerCollectionMemory(int, memory, players) => {
+        let points = self.eval_int(int)?;
+        for player in self.eval_players(players)? {
+          self.add_score_memory(&player, &memory.to_string(), points)?;
+        }
+
+        Ok(())
+      },
+    }
+  }
+
+  pub fn score(&self, player: &str) -> Result<i32, RuntimeError> {
+    Ok(self.player(player)?.score)
+  }
+
+  /// Sum of the scores of the players of the team.
+  pub fn team_score(&self, team: &str) -> Result<i32, RuntimeError> {
+    let mut score: i32 = 0;
+    for player in self.team(team)?.players.iter() {
+      score = score.checked_add(self.score(player)?).ok_or(RuntimeError::Overflow)?;
+    }
+
+    Ok(score)
+  }
+
+  /// Takes the player out of the game with the next finishing position.
+  /// A player keeps the first position they got.
+  pub fn finish(&mut self, player: &str, successful: bool) -> Result<(), RuntimeError> {
+    let position = self.players.iter().filter(|player| player.finish.is_some()).count() + 1;
+
+    let player = self.player_mut(player)?;
+    if player.finish.is_none() {
+      player.finish = Some(Finish { position, successful });
+    }
+
+    Ok(())
+  }
+
+  /// Place of the player, successful finishers take the places from the top
+  /// and eliminated players the places from the bottom, both in the order they left the game.
+  /// Players that are still in the game share the place after the last successful finisher.
+  pub fn position(&self, player: &str) -> Result<usize, RuntimeError> {
+    let left_until = |successful: bool, position: usize| {
+      self.players
+        .iter()
+        .filter(|other| matches!(other.finish, Some(finish) if finish.successful == successful && finish.position <= position))
+        .count()
+    };
+
+    match self.player(player)?.finish {
+      Some(Finish { position, successful: true }) => Ok(left_until(true, position)),
+      Some(Finish { position, successful: false }) => Ok(self.players.len() + 1 - left_until(false, position)),
+      None => Ok(left_until(true, usize::MAX) + 1),
+    }
+  }
+
+  /// Resolves the winners of a WinnerRule, tied players all win.
+  /// If every player is in a team, scores are compared per team and the whole team wins.
+  pub fn resolve_winners(&self, winner_rule: &WinnerRule) -> Result<Vec<String>, RuntimeError> {
+    match winner_rule {
+      WinnerRule::WinnerPlayer(player) => Ok(vec![self.eval_player(player)?]),
+      WinnerRule::WinnerPlayerCollection(players) => self.eval_players(players),
+      WinnerRule::WinnerLowestScore => self.score_winners(false),
+      WinnerRule::WinnerHighestScore => self.score_winners(true),
+      WinnerRule::WinnerLowestMemory(memory) => {
+        Ok(extremes(self.memory_values(&memory.to_string())?, false))
+      },
+      WinnerRule::WinnerHighestMemory(memory) => {
+        Ok(extremes(self.memory_values(&memory.to_string())?, true))
+      },
+      WinnerRule::WinnerLowestPosition => self.position_winners(false),
+      WinnerRule::WinnerHighestPosition => self.position_winners(true),
+    }
+  }
+
+  fn score_winners(&self, highest: bool) -> Result<Vec<String>, RuntimeError> {
+    let in_teams = !self.teams.is_empty()
+      && self.players.iter().all(|player| self.team_of(&player.name).is_ok());
+
+    if !in_teams {
+      let scores = self.players
+        .iter()
+        .map(|player| (player.name.clone(), player.score))
+        .collect();
+
+      return Ok(extremes(scores, highest))
+    }
+
+    let mut scores = Vec::new();
+    for team in self.teams.iter() {
+      scores.push((team.name.clone(), self.team_score(&team.name)?));
+    }
+
+    let mut winners = Vec::new();
+    for team in extremes(scores, highest) {
+      winners.extend(self.team(&team)?.players.iter().cloned());
+    }
+
+    Ok(winners)
+  }
+
+  fn position_winners(&self, highest: bool) -> Result<Vec<String>, RuntimeError> {
+    let mut positions = Vec::new();
+    for player in self.players.iter() {
+      positions.push((player.name.clone(), self.position(&player.name)? as i32));
+    }
+
+    Ok(extremes(positions, highest))
+  }
+
+  /// Teams with at least one winner.
+  pub fn winning_teams(&self) -> Vec<String> {
+    self.teams
+      .iter()
+      .filter(|team| team.players.iter().any(|player| self.winners.contains(player)))
+      .map(|team| team.name.clone())
+      .collect()
+  }
+
+  /// Final standing from first to last place, tied players share a place.
+  /// Winners come first, then players that finished successfully (in order),
+  /// then players still in the game (by score), then eliminated players (last eliminated first).
+  pub fn standing(&self) -> Vec<Vec<String>> {
+    let mut ranked = self.players
+      .iter()
+      .filter(|player| !self.winners.contains(&player.name))
+      .map(|player| {
+        let key = match player.finish {
+          Some(Finish { position, successful: true }) => (1, position as i64),
+          None => (2, -(player.score as i64)),
+          Some(Finish { position, successful: false }) => (3, -(position as i64)),
+        };
+
+        (key, player.name.clone())
+      })
+      .collect::<Vec<_>>();
+    ranked.sort_by_key(|(key, _)| *key);
+
+    let mut standing = Vec::new();
+    if !self.winners.is_empty() {
+      standing.push(self.winners.clone());
+    }
+
+    let mut last = None;
+    for (key, player) in ranked {
+      match standing.last_mut() {
+        Some(place) if last == Some(key) => place.push(player),
+        _ => standing.push(vec![player]),
+      }
+      last = Some(key);
+    }
+
+    standing
+  }
+}
+
+/// All names with the highest (or lowest) value, in their original order.
+fn extremes(values: Vec<(String, i32)>, highest: bool) -> Vec<String> {
+  let extreme = if highest {
+    values.iter().map(|(_, value)| *value).max()
+  } else {
+    values.iter().map(|(_, value)| *value).min()
+  };
+
+  values
+    .into_iter()
+    .filter(|(_, value)| Some(*value) == extreme)
+    .map(|(name, _)| name)
+    .collect()
+}
@@ -0,0 +1,318 @@
+use std::collections::BTreeMap;
+
+use ast::ast::*;
+use ir::fsm::*;
+
+use crate::error::RuntimeError;
+use crate::input::{Answer, Options};
+use crate::interpreter::{Interpreter, Step, build_fsm};
+use crate::replay::Event;
+use crate::rng::Rng;
+use crate::watchdog::{Progress, Stall, Watchdog};
+
+/// Plays a game many times with agents that pick a random legal action (`Options::sample`).
+/// A game is reproduced by its seed, its agents draw from the complement of the seed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Simulator {
+  pub games: usize,
+  /// Seed of the whole batch, the games and agents draw their seeds from it.
+  pub seed: u64,
+  /// Games that take more FSM steps are stopped and counted as unfinished.
+  pub max_steps: usize,
+  /// Games that stall between two inputs are stopped and reported with the stall.
+  pub watchdog: Watchdog,
+}
+
+impl Default for Simulator {
+  fn default() -> Self {
+    Simulator {
+      games: 1000,
+      seed: 0,
+      max_steps: 100_000,
+      watchdog: Watchdog::default(),
+    }
+  }
+}
+
+/// How often each value was observed.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Distribution {
+  pub counts: BTreeMap<usize, usize>,
+}
+
+impl Distribution {
+  pub fn add(&mut self, value: usize) {
+    *self.counts.entry(value).or_default() += 1;
+  }
+
+  pub fn samples(&self) -> usize {
+    self.counts.values().sum()
+  }
+
+  pub fn min(&self) -> Option<usize> {
+    self.counts.keys().next().copied()
+  }
+
+  pub fn max(&self) -> Option<usize> {
+    self.counts.keys().next_back().copied()
+  }
+
+  pub fn mean(&self) -> Option<f64> {
+    let samples = self.samples();
+    if samples == 0 {
+      return None
+    }
+
+    let total: usize = self.counts.iter().map(|(value, count)| value * count).sum();
+
+    Some(total as f64 / samples as f64)
+  }
+}
+
+/// How often an optional rule was entered and skipped.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OptionalCount {
+  pub entered: usize,
+  pub skipped: usize,
+}
+
+/// Statistics of a batch of simulated games.
+/// Lengths, rounds and wins only count finished games.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Statistics {
+  pub games: usize,
+  pub finished: usize,
+  /// Seeds of the games that did not finish within `max_steps`.
+  pub unfinished: Vec<u64>,
+  /// Seeds of the games that stalled, with where and why.
+  pub stalls: Vec<(u64, Stall)>,
+  /// Seeds of the games that stopped with an error.
+  pub errors: Vec<(u64, RuntimeError)>,
+  /// Turns per game, a turn ends when the current player changes.
+  pub turns: Distribution,
+  /// Transitions of the FSM taken per game.
+  pub steps: Distribution,
+  /// Rounds a stage lasted, by the name of the stage.
+  pub stage_rounds: BTreeMap<String, Distribution>,
+  /// Wins per player.
+  pub wins: BTreeMap<String, usize>,
+  /// Wins by position in the turnorder.
+  pub seat_wins: Vec<usize>,
+  /// Wins per team, a team wins with any of its players.
+  pub team_wins: BTreeMap<String, usize>,
+  /// Finished games without a winner.
+  pub no_winner: usize,
+  /// How often each branch of a ChoiceRule was taken, including branches that never were.
+  pub choices: BTreeMap<TransitionID, usize>,
+  /// How often each OptionalRule was entered and skipped, by the transition that enters it.
+  pub optionals: BTreeMap<TransitionID, OptionalCount>,
+}
+
+impl Statistics {
+  /// Empty statistics with a zero count for every choice and optional rule of the FSM.
+  fn new(fsm: &FSM) -> Self {
+    let mut statistics = Statistics::default();
+
+    for edges in fsm.states.values() {
+      let kinds = edges
+        .iter()
+        .filter_map(|(transition_id, _)| fsm.transitions.get(transition_id).map(|kind| (*transition_id, kind)))
+        .collect::<Vec<_>>();
+      let choices = kinds
+        .iter()
+        .filter(|(_, kind)| matches!(kind, Transition::Choice))
+        .map(|(transition_id, _)| *transition_id)
+        .collect::<Vec<_>>();
+      let optionals = kinds
+        .iter()
+        .filter(|(_, kind)| matches!(kind, Transition::Optional))
+        .map(|(transition_id, _)| *transition_id)
+        .collect::<Vec<_>>();
+
+      // the same split as the pending input of the Interpreter
+      match optionals.as_slice() {
+        [enter, _] if choices.is_empty() => {
+          statistics.optionals.insert(*enter, OptionalCount::default());
+        },
+        _ => {
+          for transition_id in choices.into_iter().chain(optionals) {
+            statistics.choices.insert(transition_id, 0);
+          }
+        },
+      }
+    }
+
+    statistics
+  }
+
+  /// Share of the finished games the player won.
+  pub fn win_rate(&self, player: &str) -> f64 {
+    rate(self.wins.get(player).copied().unwrap_or(0), self.finished)
+  }
+
+  /// Share of the finished games won from the position in the turnorder.
+  pub fn seat_win_rate(&self, seat: usize) -> f64 {
+    rate(self.seat_wins.get(seat).copied().unwrap_or(0), self.finished)
+  }
+
+  /// Share of the finished games the team won.
+  pub fn team_win_rate(&self, team: &str) -> f64 {
+    rate(self.team_wins.get(team).copied().unwrap_or(0), self.finished)
+  }
+
+  fn add(&mut self, played: Played) {
+    let Played { interpreter, turns, stage_rounds, choices, optionals } = played;
+    let state = &interpreter.state;
+
+    self.finished += 1;
+    self.turns.add(turns);
+    self.steps.add(
+      interpreter.log()
+        .iter()
+        .filter(|event| matches!(event, Event::Transition { .. }))
+        .count()
+    );
+
+    for (stage, rounds) in stage_rounds {
+      self.stage_rounds.entry(stage).or_default().add(rounds);
+    }
+    for transition_id in choices {
+      *self.choices.entry(transition_id).or_default() += 1;
+    }
+    for (enter, entered) in optionals {
+      let count = self.optionals.entry(enter).or_default();
+      if entered {
+        count.entered += 1;
+      } else {
+        count.skipped += 1;
+      }
+    }
+
+    if state.winners.is_empty() {
+      self.no_winner += 1;
+    }
+    for player in state.players.iter() {
+      self.wins.entry(player.name.clone()).or_default();
+    }
+    for team in state.teams.iter() {
+      let won = team.players.iter().any(|player| state.winners.contains(player));
+      *self.team_wins.entry(team.name.clone()).or_default() += won as usize;
+    }
+    if self.seat_wins.len() < state.turnorder.len() {
+      self.seat_wins.resize(state.turnorder.len(), 0);
+    }
+    for winner in state.winners.iter() {
+      *self.wins.entry(winner.clone()).or_default() += 1;
+      if let Ok(seat) = state.turnorder_index(winner) {
+        self.seat_wins[seat] += 1;
+      }
+    }
+  }
+}
+
+fn rate(count: usize, games: usize) -> f64 {
+  if games == 0 {
+    return 0.0
+  }
+
+  count as f64 / games as f64
+}
+
+/// A finished game and what was observed while playing it.
+struct Played {
+  interpreter: Interpreter,
+  turns: usize,
+  stage_rounds: Vec<(String, usize)>,
+  choices: Vec<TransitionID>,
+  /// The transition entering the optional rule and whether it was taken.
+  optionals: Vec<(TransitionID, bool)>,
+}
+
+impl Simulator {
+  pub fn simulate(&self, game: Game) -> Result<Statistics, RuntimeError> {
+    let fsm = build_fsm(game)?;
+    let mut statistics = Statistics::new(&fsm);
+    let mut seeds = Rng::new(self.seed);
+
+    for _ in 0..self.games {
+      let seed = seeds.next_u64();
+      let mut agent = Rng::new(!seed);
+
+      statistics.games += 1;
+      match self.play(Interpreter::with_seed(fsm.clone(), seed), &mut agent) {
+        Ok(Some(played)) => statistics.add(played),
+        Ok(None) => statistics.unfinished.push(seed),
+        Err(RuntimeError::Stalled(stall)) => statistics.stalls.push((seed, *stall)),
+        Err(err) => statistics.errors.push((seed, err)),
+      }
+    }
+
+    Ok(statistics)
+  }
+
+  /// Plays the game to the end, None if it takes more than `max_steps`.
+  fn play(&self, mut interpreter: Interpreter, agent: &mut Rng) -> Result<Option<Played>, RuntimeError> {
+    let mut turns = 0;
+    let mut stage_rounds = Vec::new();
+    let mut choices = Vec::new();
+    let mut optionals = Vec::new();
+    let mut current = None;
+    let mut progress = Progress::default();
+
+    for _ in 0..self.max_steps {
+      let stages = interpreter.state.stages.clone();
+      let logged = interpreter.log().len();
+
+      let step = interpreter.step();
+      match progress.check(&self.watchdog, &interpreter, step)? {
+        Step::Finished => {
+          return Ok(Some(Played { interpreter, turns, stage_rounds, choices, optionals }))
+        },
+        Step::Transition(_) => {},
+        Step::Input(request) => {
+          let answer = request.options
+            .sample(agent)
+            .ok_or(RuntimeError::NoLegalAnswer)?;
+
+          match (&request.options, &answer) {
+            (Options::Choice(_), Answer::Transition(transition_id)) => choices.push(*transition_id),
+            (Options::Optional { enter, .. }, Answer::Transition(transition_id)) => {
+              optionals.push((*enter, transition_id == enter));
+            },
+            _ => {},
+          }
+
+          interpreter.answer(answer)?;
+        },
+      }
+
+      // the frame of a stage is gone after its exit, its rounds are taken from before the step
+      for event in interpreter.log()[logged..].iter() {
+        let exited = match event {
+          Event::Transition { transition, .. } => match interpreter.fsm().transitions.get(transition) {
+            Some(Transition::ExitStage(stage)) => interpreter.fsm().stages.get(stage).map(|info| (stage, info)),
+            _ => None,
+          },
+          _ => None,
+        };
+
+        if let Some((stage, info)) = exited {
+          let rounds = stages
+            .iter()
+            .rfind(|frame| frame.stage == *stage)
+            .map(|frame| frame.rounds.max(0) as usize)
+            .unwrap_or(0);
+          stage_rounds.push((info.name.clone(), rounds));
+        }
+      }
+
+      let player = interpreter.state.current_player().ok().map(str::to_string);
+      if player.is_some() && player != current {
+        turns += 1;
+        current = player;
+      }
+    }
+
+    Ok(None)
+  }
+}
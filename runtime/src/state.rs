@@ -0,0 +1,274 @@
+use ast::ast::{FilterExpr, Status};
+use ir::fsm::{StageID, StateID};
+use serde::{Deserialize, Serialize};
+
+use crate::error::RuntimeError;
+use crate::input::Demanded;
+use crate::memory::MemoryState;
+use crate::points::PointMapState;
+use crate::precedence::PrecedenceState;
+use crate::rng::Rng;
+use crate::scoring::Finish;
+
+pub type CardId = usize;
+
+/// Visibility of a card.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum CardStatus {
+  FaceUp,
+  FaceDown,
+  /// Only visible to the owner of the location.
+  Private,
+}
+
+impl From<&Status> for CardStatus {
+  fn from(status: &Status) -> Self {
+    match status {
+      Status::FaceUp => CardStatus::FaceUp,
+      Status::FaceDown => CardStatus::FaceDown,
+      Status::Private => CardStatus::Private,
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Hash, Serialize, Deserialize)]
+pub struct Card {
+  pub id: CardId,
+  /// Key/Value pairs in the order of the keys of the creation rule.
+  pub attributes: Vec<(String, String)>,
+  pub status: CardStatus,
+}
+
+impl Card {
+  /// Returns the value of the card for the key.
+  pub fn value(&self, key: &str) -> Option<&str> {
+    self.attributes
+      .iter()
+      .find(|(k, _)| k == key)
+      .map(|(_, value)| value.as_str())
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Owner {
+  Table,
+  Player(String),
+  Team(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Hash, Serialize, Deserialize)]
+pub struct LocationState {
+  pub name: String,
+  pub owner: Owner,
+  /// Cards on the location, the top card comes first.
+  pub cards: Vec<CardId>,
+  /// Number of tokens per token type, in the order the types arrived.
+  pub tokens: Vec<(String, usize)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Hash, Serialize, Deserialize)]
+pub struct Player {
+  pub name: String,
+  pub score: i32,
+  /// Set once the player is out of the game.
+  pub finish: Option<Finish>,
+}
+
+#[derive(Debug, Clone, PartialEq, Hash, Serialize, Deserialize)]
+pub struct Team {
+  pub name: String,
+  pub players: Vec<String>,
+}
+
+/// Bookkeeping of a SeqStage that is currently executed.
+#[derive(Debug, Clone, PartialEq, Hash, Serialize, Deserialize)]
+pub struct StageFrame {
+  pub stage: StageID,
+  /// The state of the FSM that checks the EndCondition of the stage.
+  pub entry: StateID,
+  /// Number of completed rounds (StageRoundCounter).
+  pub rounds: i32,
+  /// Players that were set out of this stage.
+  pub out_of_stage: Vec<String>,
+  /// The player whose turn the current round is, None without a current player.
+  pub turn: Option<String>,
+}
+
+impl StageFrame {
+  pub fn new(stage: StageID, entry: StateID) -> Self {
+    StageFrame {
+      stage,
+      entry,
+      rounds: 0,
+      out_of_stage: Vec::new(),
+      turn: None,
+    }
+  }
+}
+
+/// The concrete state of a running game.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct GameState {
+  pub players: Vec<Player>,
+  pub teams: Vec<Team>,
+  pub locations: Vec<LocationState>,
+  /// All cards of the game, indexed by their CardId.
+  pub cards: Vec<Card>,
+  pub point_maps: Vec<PointMapState>,
+  pub precedences: Vec<PrecedenceState>,
+  /// Names of the precedences whose runs wrap around from the highest to the lowest value (Q-K-A-2).
+  /// The DSL has no syntax for this, it is a house rule set by the host of the game.
+  pub wrap_around: Vec<String>,
+  /// Combos hold their FilterExpr, they are not saved but taken from the Game on load.
+  #[serde(skip)]
+  pub combos: Vec<(String, FilterExpr)>,
+  pub memories: Vec<MemoryState>,
+  /// Last bid of every player that has bid.
+  pub bids: Vec<(String, i32)>,
+  /// Answer to the last demand.
+  pub demanded: Option<Demanded>,
+  pub turnorder: Vec<String>,
+  /// Index into the turnorder of the player whose turn it is.
+  pub current: usize,
+  /// Active stages, the innermost stage is the last one.
+  pub stages: Vec<StageFrame>,
+  /// Winners of the game, tied players all win.
+  pub winners: Vec<String>,
+  pub game_over: bool,
+  pub rng: Rng,
+}
+
+impl GameState {
+  pub fn player(&self, name: &str) -> Result<&Player, RuntimeError> {
+    self.players
+      .iter()
+      .find(|player| player.name == name)
+      .ok_or_else(|| RuntimeError::UnknownPlayer(name.to_string()))
+  }
+
+  pub fn player_mut(&mut self, name: &str) -> Result<&mut Player, RuntimeError> {
+    self.players
+      .iter_mut()
+      .find(|player| player.name == name)
+      .ok_or_else(|| RuntimeError::UnknownPlayer(name.to_string()))
+  }
+
+  pub fn card(&self, id: CardId) -> Result<&Card, RuntimeError> {
+    self.cards.get(id).ok_or(RuntimeError::UnknownCard(id))
+  }
+
+  pub fn point_map(&self, name: &str) -> Result<&PointMapState, RuntimeError> {
+    self.point_maps
+      .iter()
+      .find(|point_map| point_map.name == name)
+      .ok_or_else(|| RuntimeError::UnknownPointMap(name.to_string()))
+  }
+
+  pub fn precedence(&self, name: &str) -> Result<&PrecedenceState, RuntimeError> {
+    self.precedences
+      .iter()
+      .find(|precedence| precedence.name == name)
+      .ok_or_else(|| RuntimeError::UnknownPrecedence(name.to_string()))
+  }
+
+  pub fn combo(&self, name: &str) -> Result<&FilterExpr, RuntimeError> {
+    self.combos
+      .iter()
+      .find(|(combo, _)| combo == name)
+      .map(|(_, filter)| filter)
+      .ok_or_else(|| RuntimeError::UnknownCombo(name.to_string()))
+  }
+
+  /// Index of the location with the name and owner.
+  pub fn location_index(&self, name: &str, owner: &Owner) -> Option<usize> {
+    self.locations
+      .iter()
+      .position(|location| location.name == name && location.owner == *owner)
+  }
+
+  /// Resolves the name of a location the way the DSL does.
+  /// With a player the location belongs to that player (or their team).
+  /// Without a player a table location is preferred, then the one of the current player (or their team).
+  pub fn resolve_location(&self, name: &str, player: Option<&str>) -> Result<usize, RuntimeError> {
+    let player = match player {
+      Some(player) => player,
+      None => {
+        if let Some(index) = self.location_index(name, &Owner::Table) {
+          return Ok(index)
+        }
+
+        self.current_player()?
+      },
+    };
+
+    if let Some(index) = self.location_index(name, &Owner::Player(player.to_string())) {
+      return Ok(index)
+    }
+
+    let team = self.team_of(player).ok()
+      .and_then(|team| self.location_index(name, &Owner::Team(team.name.clone())));
+    if let Some(index) = team {
+      return Ok(index)
+    }
+
+    Err(RuntimeError::UnknownLocation(format!("{} of {}", name, player)))
+  }
+
+  /// Index of the location the card lies on.
+  pub fn location_of(&self, card: CardId) -> Result<usize, RuntimeError> {
+    self.locations
+      .iter()
+      .position(|location| location.cards.contains(&card))
+      .ok_or(RuntimeError::UnknownCard(card))
+  }
+
+  pub fn team(&self, name: &str) -> Result<&Team, RuntimeError> {
+    self.teams
+      .iter()
+      .find(|team| team.name == name)
+      .ok_or_else(|| RuntimeError::UnknownTeam(name.to_string()))
+  }
+
+  /// Returns the team the player belongs to.
+  pub fn team_of(&self, player: &str) -> Result<&Team, RuntimeError> {
+    self.teams
+      .iter()
+      .find(|team| team.players.iter().any(|p| p == player))
+      .ok_or_else(|| RuntimeError::UnknownTeam(format!("team of {}", player)))
+  }
+
+  /// Name of the player whose turn it is.
+  pub fn current_player(&self) -> Result<&str, RuntimeError> {
+    self.turnorder
+      .get(self.current)
+      .map(|name| name.as_str())
+      .ok_or(RuntimeError::NoCurrentPlayer)
+  }
+
+  /// Position of the player in the turnorder.
+  pub fn turnorder_index(&self, player: &str) -> Result<usize, RuntimeError> {
+    self.turnorder
+      .iter()
+      .position(|name| name == player)
+      .ok_or_else(|| RuntimeError::UnknownPlayer(player.to_string()))
+  }
+
+  /// The innermost active stage.
+  pub fn stage(&self) -> Result<&StageFrame, RuntimeError> {
+    self.stages.last().ok_or(RuntimeError::NoActiveStage)
+  }
+
+  pub fn stage_mut(&mut self) -> Result<&mut StageFrame, RuntimeError> {
+    self.stages.last_mut().ok_or(RuntimeError::NoActiveStage)
+  }
+
+  pub fn is_out_of_stage(&self, player: &str) -> bool {
+    self.stages
+      .last()
+      .is_some_and(|stage| stage.out_of_stage.iter().any(|p| p == player))
+  }
+
+  pub fn is_out_of_game(&self, player: &str) -> Result<bool, RuntimeError> {
+    Ok(self.player(player)?.finish.is_some())
+  }
+}
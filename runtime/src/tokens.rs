@@ -0,0 +1,184 @@
+use ast::ast::*;
+
+use crate::error::RuntimeError;
+use crate::input::Answer;
+use crate::state::GameState;
+
+impl GameState {
+  /// Creates the tokens on every location with the name.
+  pub fn create_tokens(&mut self, amount: &IntExpr, token: &str, location: &str) -> Result<(), RuntimeError> {
+    let amount = self.eval_int(amount)?;
+    let amount = usize::try_from(amount).map_err(|_| RuntimeError::NegativeAmount(amount))?;
+
+    let locations = (0..self.locations.len())
+      .filter(|index| self.locations[*index].name == location)
+      .collect::<Vec<_>>();
+    if locations.is_empty() {
+      return Err(RuntimeError::UnknownLocation(location.to_string()))
+    }
+
+    for index in locations {
+      self.put_tokens(index, token, amount);
+    }
+
+    Ok(())
+  }
+
+  /// Number of tokens on the location, of every type.
+  pub fn tokens_on(&self, location: usize) -> usize {
+    self.locations[location].tokens
+      .iter()
+      .map(|(_, amount)| amount)
+      .sum()
+  }
+
+  /// Number of tokens of the type on the location.
+  pub fn tokens_of(&self, location: usize, token: &str) -> usize {
+    self.locations[location].tokens
+      .iter()
+      .find(|(name, _)| name == token)
+      .map(|(_, amount)| *amount)
+      .unwrap_or(0)
+  }
+
+  /// The locations a TokenLocExpr refers to, one per player of a PlayerCollection.
+  pub fn token_locations(&self, token_loc: &TokenLocExpr) -> Result<Vec<usize>, RuntimeError> {
+    match token_loc {
+      TokenLocExpr::Location(location) => {
+        Ok(vec![self.resolve_location(&location.to_string(), None)?])
+      },
+      TokenLocExpr::LocationCollection(locations) => {
+        locations.locations
+          .iter()
+          .map(|location| self.resolve_location(&location.to_string(), None))
+          .collect()
+      },
+      TokenLocExpr::LocationPlayer(location, player) => {
+        let player = self.eval_player(player)?;
+
+        Ok(vec![self.resolve_location(&location.to_string(), Some(&player))?])
+      },
+      TokenLocExpr::LocationCollectionPlayer(locations, player) => {
+        let player = self.eval_player(player)?;
+
+        locations.locations
+          .iter()
+          .map(|location| self.resolve_location(&location.to_string(), Some(&player)))
+          .collect()
+      },
+      TokenLocExpr::LocationPlayerCollection(location, players) => {
+        self.eval_players(players)?
+          .iter()
+          .map(|player| self.resolve_location(&location.to_string(), Some(player)))
+          .collect()
+      },
+      TokenLocExpr::LocationCollectionPlayerCollection(locations, players) => {
+        let mut indices = Vec::new();
+        for player in self.eval_players(players)? {
+          for location in locations.locations.iter() {
+            indices.push(self.resolve_location(&location.to_string(), Some(&player))?);
+          }
+        }
+
+        Ok(indices)
+      },
+    }
+  }
+
+  /// Pairs the source and target locations of a TokenMove.
+  /// A single source pays every target, a single target is paid by every source,
+  /// otherwise sources and targets are paired in order.
+  fn token_transfers(&self, from: &TokenLocExpr, to: &TokenLocExpr) -> Result<Vec<(usize, usize)>, RuntimeError> {
+    let from = self.token_locations(from)?;
+    let to = self.token_locations(to)?;
+
+    match (from.as_slice(), to.as_slice()) {
+      ([source], targets) => Ok(targets.iter().map(|target| (*source, *target)).collect()),
+      (sources, [target]) => Ok(sources.iter().map(|source| (*source, *target)).collect()),
+      (sources, targets) if sources.len() == targets.len() => {
+        Ok(sources.iter().copied().zip(targets.iter().copied()).collect())
+      },
+      _ => Err(RuntimeError::AmbiguousLocation(format!("{:?} to {:?}", from, to))),
+    }
+  }
+
+  /// Amounts the Quantity allows for a TokenMove, every transfer moves the same amount.
+  pub fn token_amounts(&self, quantity: &Quantity, from: &TokenLocExpr, to: &TokenLocExpr) -> Result<Vec<usize>, RuntimeError> {
+    let transfers = self.token_transfers(from, to)?;
+    let available = transfers
+      .iter()
+      .map(|(source, _)| self.tokens_on(*source) / payments(&transfers, *source))
+      .min()
+      .unwrap_or(0);
+
+    self.counts(quantity, available).map_err(|err| match err {
+      RuntimeError::NotEnoughCards { needed, available } => RuntimeError::NotEnoughTokens { needed, available },
+      err => err,
+    })
+  }
+
+  /// Executes a TokenMove, nothing is moved if a source has too few tokens.
+  /// Without a Quantity a source splits all of its tokens evenly among its targets,
+  /// the remainder of the split stays on the source.
+  pub fn place_tokens(&mut self, token_move: &TokenMove, answer: Option<&Answer>) -> Result<(), RuntimeError> {
+    let (from, to, amount) = match token_move {
+      TokenMove::Place(from, to) => (from, to, None),
+      TokenMove::PlaceQuantity(quantity, from, to) => {
+        let amounts = self.token_amounts(quantity, from, to)?;
+        let amount = match (amounts.as_slice(), answer) {
+          ([amount], _) => *amount,
+          (_, Some(Answer::Amount(amount))) if amounts.contains(amount) => *amount,
+          (_, None) => return Err(RuntimeError::UndecidedQuantity),
+          _ => return Err(RuntimeError::InvalidQuantity),
+        };
+
+        (from, to, Some(amount))
+      },
+    };
+
+    let transfers = self.token_transfers(from, to)?;
+    let amounts = transfers
+      .iter()
+      .map(|(source, _)| amount.unwrap_or_else(|| self.tokens_on(*source) / payments(&transfers, *source)))
+      .collect::<Vec<_>>();
+
+    for ((source, target), amount) in transfers.into_iter().zip(amounts) {
+      self.transfer_tokens(source, target, amount);
+    }
+
+    Ok(())
+  }
+
+  /// Takes the tokens in the order of their types and puts them on the target.
+  fn transfer_tokens(&mut self, source: usize, target: usize, mut amount: usize) {
+    let mut taken = Vec::new();
+    for (token, available) in self.locations[source].tokens.iter_mut() {
+      let take = amount.min(*available);
+      *available -= take;
+      amount -= take;
+      taken.push((token.clone(), take));
+    }
+    self.locations[source].tokens.retain(|(_, available)| *available > 0);
+
+    for (token, amount) in taken {
+      self.put_tokens(target, &token, amount);
+    }
+  }
+
+  fn put_tokens(&mut self, location: usize, token: &str, amount: usize) {
+    if amount == 0 {
+      return
+    }
+
+    let tokens = &mut self.locations[location].tokens;
+    match tokens.iter_mut().find(|(name, _)| name == token) {
+      Some((_, available)) => *available += amount,
+      None => tokens.push((token.to_string(), amount)),
+    }
+  }
+}
+
+/// Number of transfers paid by the source.
+fn payments(transfers: &[(usize, usize)], source: usize) -> usize {
+  transfers.iter().filter(|(other, _)| *other == source).count()
+}
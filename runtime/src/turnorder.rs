@@ -0,0 +1,108 @@
+use crate::error::RuntimeError;
+use crate::state::GameState;
+
+impl GameState {
+  /// A player takes part in the turnorder unless they are out of the game or out of the current stage.
+  pub fn is_active(&self, player: &str) -> Result<bool, RuntimeError> {
+    Ok(!self.is_out_of_game(player)? && !self.is_out_of_stage(player))
+  }
+
+  /// The turnorder without the players that are out.
+  pub fn active_turnorder(&self) -> Result<Vec<String>, RuntimeError> {
+    let mut active = Vec::new();
+    for player in self.turnorder.iter() {
+      if self.is_active(player)? {
+        active.push(player.clone());
+      }
+    }
+
+    Ok(active)
+  }
+
+  /// First active player after (forward) or before (backward) the current player.
+  /// Falls back to the current player if everybody else is out.
+  fn step_turnorder(&self, forward: bool) -> Result<String, RuntimeError> {
+    self.current_player()?;
+    let len = self.turnorder.len();
+
+    for offset in 1..len {
+      let index = if forward {
+        (self.current + offset) % len
+      } else {
+        (self.current + len - offset) % len
+      };
+
+      if self.is_active(&self.turnorder[index])? {
+        return Ok(self.turnorder[index].clone())
+      }
+    }
+
+    let current = self.current_player()?;
+    if self.is_active(current)? {
+      return Ok(current.to_string())
+    }
+
+    Err(RuntimeError::NoActivePlayer)
+  }
+
+  /// The next active player in the turnorder.
+  pub fn next_player(&self) -> Result<String, RuntimeError> {
+    self.step_turnorder(true)
+  }
+
+  /// The previous active player in the turnorder.
+  pub fn previous_player(&self) -> Result<String, RuntimeError> {
+    self.step_turnorder(false)
+  }
+
+  /// Ends the turn of the current player, the turn passes to the next active player.
+  /// In a stage the turn of a round passes only once: not again if `end turn` or a cycle passed it already.
+  pub fn pass_turn(&mut self) -> Result<(), RuntimeError> {
+    if let Some(frame) = self.stages.last()
+      && (frame.turn.is_none() || frame.turn.as_deref() != self.current_player().ok()) {
+      return Ok(())
+    }
+
+    let player = self.next_player()?;
+    self.current = self.turnorder_index(&player)?;
+
+    Ok(())
+  }
+
+  /// Starts a round of the innermost stage with the turn of the current player.
+  pub fn start_turn(&mut self) -> Result<(), RuntimeError> {
+    let player = self.current_player().ok().map(str::to_string);
+    self.stage_mut()?.turn = player;
+
+    Ok(())
+  }
+
+  /// The player at the position of the turnorder, counting only active players.
+  pub fn turnorder_at(&self, index: i32) -> Result<String, RuntimeError> {
+    let active = self.active_turnorder()?;
+    let len = active.len();
+
+    usize::try_from(index)
+      .ok()
+      .and_then(|index| active.get(index))
+      .cloned()
+      .ok_or(RuntimeError::IndexOutOfRange { index, len })
+  }
+
+  /// The next active player in the turnorder that is not in the team of the current player.
+  /// In a two player game this is the other player, without teams it is the next player.
+  pub fn competitor(&self) -> Result<String, RuntimeError> {
+    let current = self.current_player()?;
+    let team = self.team_of(current).ok().map(|team| team.players.clone()).unwrap_or_default();
+    let len = self.turnorder.len();
+
+    for offset in 1..len {
+      let player = &self.turnorder[(self.current + offset) % len];
+      if !team.contains(player) && self.is_active(player)? {
+        return Ok(player.clone())
+      }
+    }
+
+    Err(RuntimeError::NoCompetitor)
+  }
+}
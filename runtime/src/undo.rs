@@ -0,0 +1,112 @@
+use ir::fsm::StateID;
+
+use crate::error::RuntimeError;
+use crate::input::Answer;
+use crate::interpreter::Interpreter;
+use crate::replay::Event;
+use crate::state::GameState;
+
+/// A decision a player took, the game can be rolled back to the moment before it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Decision {
+  pub player: String,
+  pub answer: Answer,
+  current_state: StateID,
+  state: GameState,
+}
+
+/// An undone decision and the game as it was before the undo, until it is redone or a new decision is taken.
+#[derive(Debug, Clone, PartialEq)]
+struct Undone {
+  decision: Decision,
+  current_state: StateID,
+  state: GameState,
+}
+
+/// Undo and redo stacks of the decisions of a game.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct History {
+  decisions: Vec<Decision>,
+  undone: Vec<Undone>,
+}
+
+impl Interpreter {
+  /// The moment before the answer, to be recorded once the answer was taken.
+  pub(crate) fn decision_point(&self, player: &str, answer: &Answer) -> Decision {
+    Decision {
+      player: player.to_string(),
+      answer: answer.clone(),
+      current_state: self.current_state,
+      state: self.state.clone(),
+    }
+  }
+
+  /// A new decision ends the redo stack.
+  pub(crate) fn record_decision(&mut self, decision: Decision) {
+    self.history.decisions.push(decision);
+    self.history.undone.clear();
+  }
+
+  /// The decisions taken so far, oldest first.
+  pub fn decisions(&self) -> &[Decision] {
+    &self.history.decisions
+  }
+
+  /// Rolls back the last decision and every rule executed after it, its input is pending again.
+  /// The undone events stay in the log, followed by an Undo event.
+  ///
+  /// The random number generator is reseeded, so a shuffle after the decision point
+  /// does not repeat the card order the undone branch has revealed.
+  pub fn undo(&mut self) -> Result<(), RuntimeError> {
+    let decision = self.history.decisions.pop().ok_or(RuntimeError::NothingToUndo)?;
+
+    // the undone branch has drawn from the generator, its next draw was never used
+    let seed = self.state.rng.clone().next_u64();
+
+    self.history.undone.push(
+      Undone {
+        current_state: std::mem::replace(&mut self.current_state, decision.current_state),
+        state: std::mem::replace(&mut self.state, decision.state.clone()),
+        decision,
+      }
+    );
+    self.log.push(Event::Undo);
+    self.reseed(seed);
+
+    Ok(())
+  }
+
+  /// Takes the last undone decision again, with the same outcome as before the undo.
+  pub fn redo(&mut self) -> Result<(), RuntimeError> {
+    let undone = self.history.undone.pop().ok_or(RuntimeError::NothingToRedo)?;
+
+    self.current_state = undone.current_state;
+    self.state = undone.state;
+    self.history.decisions.push(undone.decision);
+    self.log.push(Event::Redo);
+    self.log_diff();
+
+    Ok(())
+  }
+
+  /// Undoes decisions until only the first `decision` ones are left.
+  /// Answering from there branches off and drops the undone decisions.
+  pub fn undo_to(&mut self, decision: usize) -> Result<(), RuntimeError> {
+    if decision > self.history.decisions.len() {
+      return Err(RuntimeError::UnknownDecision(decision))
+    }
+
+    while self.history.decisions.len() > decision {
+      self.undo()?;
+    }
+
+    Ok(())
+  }
+
+  /// Continues the random draws from the seed and logs it, replays reseed at the same event.
+  pub(crate) fn reseed(&mut self, seed: u64) {
+    self.state.rng.reseed(seed);
+    self.log.push(Event::Reseed(seed));
+    self.log_diff();
+  }
+}
@@ -0,0 +1,152 @@
+use crate::error::RuntimeError;
+use crate::memory::{CollectionValue, MemoryState, MemoryType, MemoryValue};
+use crate::state::{Card, CardId, CardStatus, GameState, Owner, Player, Team};
+
+/// A card as one player sees it.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CardView {
+  Known(Card),
+  /// A face down card or a private card of someone else, neither its values nor its id are shown.
+  Unknown,
+}
+
+/// The value of a memory as one player sees it.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MemoryValueView {
+  /// Any value without cards.
+  Value(MemoryValue),
+  /// A collection of cards, shown like the cards of a location.
+  Cards(Vec<CardView>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MemoryView {
+  pub name: String,
+  pub owner: Owner,
+  pub memory_type: Option<MemoryType>,
+  pub value: Option<MemoryValueView>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LocationView {
+  pub name: String,
+  pub owner: Owner,
+  /// Cards on the location, the top card comes first.
+  pub cards: Vec<CardView>,
+  pub tokens: Vec<(String, usize)>,
+}
+
+/// The game state as one player sees it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlayerView {
+  /// The player the view belongs to.
+  pub player: String,
+  pub players: Vec<Player>,
+  pub teams: Vec<Team>,
+  pub locations: Vec<LocationView>,
+  pub memories: Vec<MemoryView>,
+  pub bids: Vec<(String, i32)>,
+  pub turnorder: Vec<String>,
+  pub current: usize,
+  pub winners: Vec<String>,
+  pub game_over: bool,
+}
+
+impl GameState {
+  /// Checks if the player can see the card.
+  /// Face up cards are seen by everyone, face down cards by nobody,
+  /// private cards by the owner of their location (every member for a team location).
+  pub fn is_visible(&self, card: CardId, player: &str) -> Result<bool, RuntimeError> {
+    let location = &self.locations[self.location_of(card)?];
+
+    match self.card(card)?.status {
+      CardStatus::FaceUp => Ok(true),
+      CardStatus::FaceDown => Ok(false),
+      CardStatus::Private => match &location.owner {
+        Owner::Player(owner) => Ok(owner == player),
+        Owner::Team(team) => Ok(self.team(team)?.players.iter().any(|member| member == player)),
+        Owner::Table => Ok(false),
+      },
+    }
+  }
+
+  /// Projects the game state on what the player can see.
+  /// Hidden cards keep their place on the location, so the number of cards is always known.
+  pub fn view(&self, player: &str) -> Result<PlayerView, RuntimeError> {
+    self.player(player)?;
+
+    let mut locations = Vec::new();
+    for location in self.locations.iter() {
+      locations.push(
+        LocationView {
+          name: location.name.clone(),
+          owner: location.owner.clone(),
+          cards: self.card_views(&location.cards, player)?,
+          tokens: location.tokens.clone(),
+        }
+      );
+    }
+
+    let mut memories = Vec::new();
+    for memory in self.memories.iter() {
+      memories.push(self.memory_view(memory, player)?);
+    }
+
+    Ok(
+      PlayerView {
+        player: player.to_string(),
+        players: self.players.clone(),
+        teams: self.teams.clone(),
+        locations,
+        memories,
+        bids: self.bids.clone(),
+        turnorder: self.turnorder.clone(),
+        current: self.current,
+        winners: self.winners.clone(),
+        game_over: self.game_over,
+      }
+    )
+  }
+
+  fn card_views(&self, cards: &[CardId], player: &str) -> Result<Vec<CardView>, RuntimeError> {
+    let mut views = Vec::new();
+    for card in cards.iter() {
+      if self.is_visible(*card, player)? {
+        views.push(CardView::Known(self.card(*card)?.clone()));
+      } else {
+        views.push(CardView::Unknown);
+      }
+    }
+
+    Ok(views)
+  }
+
+  /// Cards stored in a memory are shown like the cards of a location, hidden ones without their id.
+  fn memory_view(&self, memory: &MemoryState, player: &str) -> Result<MemoryView, RuntimeError> {
+    let value = match &memory.value {
+      Some(MemoryValue::Collection(CollectionValue::Cards(cards))) => {
+        Some(MemoryValueView::Cards(self.card_views(cards, player)?))
+      },
+      Some(value) => Some(MemoryValueView::Value(value.clone())),
+      None => None,
+    };
+
+    Ok(
+      MemoryView {
+        name: memory.name.clone(),
+        owner: memory.owner.clone(),
+        memory_type: memory.memory_type,
+        value,
+      }
+    )
+  }
+}
+
+impl PlayerView {
+  /// A location of the owner as the player of the view sees it.
+  pub fn location(&self, name: &str, owner: &Owner) -> Option<&LocationView> {
+    self.locations
+      .iter()
+      .find(|location| location.name == name && location.owner == *owner)
+  }
+}
@@ -0,0 +1,516 @@
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+use ast::ast::*;
+use ir::fsm::*;
+
+use crate::error::RuntimeError;
+use crate::interpreter::{Interpreter, Step};
+
+/// Limits of `Interpreter::run_watched`, a game beyond them is reported as stalled instead of hanging.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Watchdog {
+  /// Steps without a player input.
+  pub max_steps: usize,
+  /// How often the game may return to the same state at the end of a stage round.
+  /// Conditions can read the `stageroundcounter`, so a single repetition is not yet a loop.
+  pub max_repeats: usize,
+}
+
+impl Default for Watchdog {
+  fn default() -> Self {
+    Watchdog {
+      max_steps: 100_000,
+      max_repeats: 100,
+    }
+  }
+}
+
+/// Why a game stopped making progress.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StallReason {
+  /// The state at the end of a stage round repeated more than `max_repeats` times without an input.
+  /// Rounds of stages that end after a number of repetitions or read the `stageroundcounter`
+  /// are part of the state, all others are not.
+  Loop { repeats: usize },
+  /// Nothing can be done: the pending input has no legal answer (None),
+  /// or the next rule failed because it can not be satisfied.
+  NoLegalAction(Option<RuntimeError>),
+  /// `max_steps` steps without an input.
+  StepBudget(usize),
+}
+
+/// Where and why a game stalled.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stall {
+  pub reason: StallReason,
+  /// The FSM state the game is stuck in.
+  pub state: StateID,
+  /// Name of the innermost stage, None outside of stages.
+  pub stage: Option<String>,
+  /// Steps since the last input.
+  pub steps: usize,
+}
+
+impl fmt::Display for Stall {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match &self.reason {
+      StallReason::Loop { repeats } => write!(f, "loop, the state repeated {} times", repeats)?,
+      StallReason::NoLegalAction(None) => write!(f, "no legal action")?,
+      StallReason::NoLegalAction(Some(err)) => write!(f, "no legal action ({})", err)?,
+      StallReason::StepBudget(steps) => write!(f, "no input for {} steps", steps)?,
+    }
+
+    write!(f, " in state {}", self.state)?;
+    if let Some(stage) = &self.stage {
+      write!(f, " of stage {}", stage)?;
+    }
+
+    Ok(())
+  }
+}
+
+/// What a Watchdog has seen since the last input.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Progress {
+  steps: usize,
+  /// Round hashes and how often they were seen.
+  seen: HashMap<u64, usize>,
+  /// Whether the rounds of a stage are part of its round hash, see `Interpreter::counts_rounds`.
+  counted: HashMap<StageID, bool>,
+}
+
+impl Progress {
+  /// Checks the outcome of a step, a stalled game is turned into `RuntimeError::Stalled`.
+  pub(crate) fn check(
+    &mut self,
+    watchdog: &Watchdog,
+    interpreter: &Interpreter,
+    step: Result<Step, RuntimeError>
+  ) -> Result<Step, RuntimeError> {
+    let step = match step {
+      Ok(step) => step,
+      Err(err) if is_unsatisfiable(&err) => {
+        return Err(interpreter.stall(StallReason::NoLegalAction(Some(err)), self.steps))
+      },
+      Err(err) => return Err(err),
+    };
+
+    match &step {
+      Step::Transition(transition_id) => {
+        self.steps += 1;
+
+        if let Some(Transition::StageCounter) = interpreter.fsm().transitions.get(transition_id) {
+          let hash = interpreter.round_hash(&mut self.counted);
+          let repeats = self.seen.entry(hash).or_default();
+          *repeats += 1;
+          if *repeats > watchdog.max_repeats {
+            let repeats = *repeats;
+            return Err(interpreter.stall(StallReason::Loop { repeats }, self.steps))
+          }
+        }
+        if self.steps >= watchdog.max_steps {
+          return Err(interpreter.stall(StallReason::StepBudget(watchdog.max_steps), self.steps))
+        }
+      },
+      Step::Input(request) => {
+        if request.options.is_empty() {
+          return Err(interpreter.stall(StallReason::NoLegalAction(None), self.steps))
+        }
+
+        // the answer may change anything, the game starts over from the input
+        *self = Progress {
+          counted: std::mem::take(&mut self.counted),
+          ..Progress::default()
+        };
+      },
+      Step::Finished => {},
+    }
+
+    Ok(step)
+  }
+}
+
+impl Interpreter {
+  /// Like `run`, but reports a game that stopped making progress as `RuntimeError::Stalled`.
+  pub fn run_watched(&mut self, watchdog: &Watchdog) -> Result<Step, RuntimeError> {
+    let mut progress = Progress::default();
+
+    loop {
+      let step = self.step();
+      match progress.check(watchdog, self, step)? {
+        Step::Transition(_) => continue,
+        step => return Ok(step),
+      }
+    }
+  }
+
+  fn stall(&self, reason: StallReason, steps: usize) -> RuntimeError {
+    RuntimeError::Stalled(Box::new(
+      Stall {
+        reason,
+        state: self.current_state(),
+        stage: self.state.stages
+          .last()
+          .and_then(|frame| self.fsm().stages.get(&frame.stage))
+          .map(|stage| stage.name.clone()),
+        steps,
+      }
+    ))
+  }
+
+  /// Hash of the FSM state and the game state, without the rounds of stages that do not count them.
+  fn round_hash(&self, counted: &mut HashMap<StageID, bool>) -> u64 {
+    let state = &self.state;
+    let mut hasher = DefaultHasher::new();
+    self.current_state().hash(&mut hasher);
+
+    // combos are taken from the Game and do not change while it is played
+    state.players.hash(&mut hasher);
+    state.teams.hash(&mut hasher);
+    state.locations.hash(&mut hasher);
+    state.cards.hash(&mut hasher);
+    state.point_maps.hash(&mut hasher);
+    state.precedences.hash(&mut hasher);
+    state.wrap_around.hash(&mut hasher);
+    state.memories.hash(&mut hasher);
+    state.bids.hash(&mut hasher);
+    state.demanded.hash(&mut hasher);
+    state.turnorder.hash(&mut hasher);
+    state.current.hash(&mut hasher);
+    state.winners.hash(&mut hasher);
+    state.game_over.hash(&mut hasher);
+    state.rng.hash(&mut hasher);
+
+    for frame in state.stages.iter() {
+      let counts = *counted
+        .entry(frame.stage)
+        .or_insert_with(|| self.counts_rounds(frame.stage, frame.entry));
+
+      frame.stage.hash(&mut hasher);
+      frame.entry.hash(&mut hasher);
+      if counts {
+        frame.rounds.hash(&mut hasher);
+      }
+      frame.out_of_stage.hash(&mut hasher);
+      frame.turn.hash(&mut hasher);
+    }
+
+    hasher.finish()
+  }
+
+  /// Whether the stage depends on its repetitions:
+  /// its end condition counts them, or the condition or a rule of the stage reads the `stageroundcounter`.
+  fn counts_rounds(&self, stage: StageID, entry: StateID) -> bool {
+    let fsm = self.fsm();
+
+    let conditions = fsm.states
+      .get(&entry)
+      .into_iter()
+      .flatten()
+      .map(|(transition_id, _)| transition_id);
+    let rules = fsm.transition_stages
+      .iter()
+      .filter(|(_, transition_stage)| **transition_stage == stage)
+      .map(|(transition_id, _)| transition_id);
+
+    let reads = RoundReads::new(&self.state.combos);
+    conditions
+      .chain(rules)
+      .filter_map(|transition_id| fsm.transitions.get(transition_id))
+      .any(|transition| match transition {
+        Transition::EndCondition(end_condition) | Transition::NotEndCondition(end_condition)
+          if !matches!(end_condition, EndCondition::UntilBool(_) | EndCondition::UntilEnd) => true,
+        transition => reads.transition(transition),
+      })
+  }
+}
+
+/// Searches the expressions of the AST for `IntExpr::StageRoundCounter`.
+struct RoundReads {
+  /// Whether a reference to a combo reads the `stageroundcounter`.
+  /// Combos are only named by the rules, any combo that reads it counts.
+  combos: bool,
+}
+
+impl RoundReads {
+  fn new(combos: &[(String, FilterExpr)]) -> Self {
+    let combos = combos
+      .iter()
+      .any(|(_, filter)| RoundReads { combos: false }.filter(filter));
+
+    RoundReads { combos }
+  }
+
+  /// Whether the transition reads the `stageroundcounter` of its stage.
+  fn transition(&self, transition: &Transition) -> bool {
+    match transition {
+      Transition::Action(rule) => self.rule(rule),
+      Transition::Condition(bool_expr) | Transition::NotCondition(bool_expr) => self.bool(bool_expr),
+      Transition::EndCondition(end_condition) | Transition::NotEndCondition(end_condition) => {
+        self.end_condition(end_condition)
+      },
+      Transition::StageCounter
+        | Transition::Optional
+        | Transition::Choice
+        | Transition::EnterStage(_)
+        | Transition::ExitStage(_) => false,
+    }
+  }
+
+  fn rule(&self, rule: &Rule) -> bool {
+    match rule {
+      Rule::CreateCardOnLocation(_, _)
+        | Rule::CreatePlayer(_)
+        | Rule::CreateTeam(_, _)
+        | Rule::CreateTurnorder(_)
+        | Rule::CreateTurnorderRandom(_)
+        | Rule::CreateLocationOnTable(_)
+        | Rule::CreateLocationCollectionOnTable(_)
+        | Rule::CreatePrecedence(_, _)
+        | Rule::CreatePrecedencePairs(_, _)
+        | Rule::CreatePointMap(_, _)
+        | Rule::CreateMemoryTable(_)
+        | Rule::EndTurn
+        | Rule::EndStage => false,
+      Rule::CreateLocationOnPlayerCollection(_, players)
+        | Rule::CreateLocationCollectionOnPlayerCollection(_, players)
+        | Rule::CreateMemoryPlayerCollection(_, players)
+        | Rule::PlayerCollectionOutOfStageAction(players)
+        | Rule::PlayerCollectionOutOfGameSuccAction(players)
+        | Rule::PlayerCollectionOutOfGameFailAction(players) => self.players(players),
+      Rule::CreateLocationOnTeamCollection(_, teams)
+        | Rule::CreateLocationCollectionOnTeamCollection(_, teams) => self.teams(teams),
+      Rule::CreateTokenOnLocation(int, _, _)
+        | Rule::CreateMemoryIntTable(_, int)
+        | Rule::SetMemoryInt(_, int)
+        | Rule::DemandIntAction(int) => self.int(int),
+      Rule::CreateCombo(_, filter) => self.filter(filter),
+      Rule::CreateMemoryIntPlayerCollection(_, int, players) => self.int(int) || self.players(players),
+      Rule::CreateMemoryStringPlayerCollection(_, string, players) => {
+        self.string(string) || self.players(players)
+      },
+      Rule::CreateMemoryStringTable(_, string)
+        | Rule::SetMemoryString(_, string)
+        | Rule::DemandStringAction(string) => self.string(string),
+      Rule::CreatePointMapPairs(_, pairs) => pairs.key_value_int_vec.iter().any(|(_, _, int)| self.int(int)),
+      Rule::FlipAction(card_set, _) | Rule::ShuffleAction(card_set) => self.card_set(card_set),
+      Rule::PlayerOutOfStageAction(player)
+        | Rule::PlayerOutOfGameSuccAction(player)
+        | Rule::PlayerOutOfGameFailAction(player)
+        | Rule::CycleAction(player)
+        | Rule::EndGameWithWinner(player) => self.player(player),
+      Rule::SetMemoryCollection(_, collection) => self.collection(collection),
+      Rule::BidAction(quantity) | Rule::BidActionMemory(_, quantity) => self.quantity(quantity),
+      Rule::DemandCardPositionAction(card_position) => self.card_position(card_position),
+      Rule::ClassicMove(ClassicMove::Move(from, _, to))
+        | Rule::DealMove(DealMove::Deal(from, _, to))
+        | Rule::ExchangeMove(ExchangeMove::Exchange(from, _, to)) => self.card_set(from) || self.card_set(to),
+      Rule::ClassicMove(ClassicMove::MoveQuantity(quantity, from, _, to))
+        | Rule::DealMove(DealMove::DealQuantity(quantity, from, _, to))
+        | Rule::ExchangeMove(ExchangeMove::ExchangeQuantity(quantity, from, _, to)) => {
+        self.quantity(quantity) || self.card_set(from) || self.card_set(to)
+      },
+      Rule::TokenMove(TokenMove::Place(from, to)) => self.token_loc(from) || self.token_loc(to),
+      Rule::TokenMove(TokenMove::PlaceQuantity(quantity, from, to)) => {
+        self.quantity(quantity) || self.token_loc(from) || self.token_loc(to)
+      },
+      Rule::ScoreRule(ScoreRule::ScorePlayer(int, player))
+        | Rule::ScoreRule(ScoreRule::ScorePlayerMemory(int, _, player)) => self.int(int) || self.player(player),
+      Rule::ScoreRule(ScoreRule::ScorePlayerCollection(int, players))
+        | Rule::ScoreRule(ScoreRule::ScorePlayerCollectionMemory(int, _, players)) => {
+        self.int(int) || self.players(players)
+      },
+      Rule::WinnerRule(WinnerRule::WinnerPlayer(player)) => self.player(player),
+      Rule::WinnerRule(WinnerRule::WinnerPlayerCollection(players)) => self.players(players),
+      Rule::WinnerRule(_) => false,
+    }
+  }
+
+  fn end_condition(&self, end_condition: &EndCondition) -> bool {
+    match end_condition {
+      EndCondition::UntilBool(bool_expr) => self.bool(bool_expr),
+      EndCondition::UntilBoolAndRep(bool_expr, reps) | EndCondition::UntilBoolOrRep(bool_expr, reps) => {
+        self.bool(bool_expr) || self.int(&reps.times)
+      },
+      EndCondition::UntilRep(reps) => self.int(&reps.times),
+      EndCondition::UntilEnd => false,
+    }
+  }
+
+  fn bool(&self, bool_expr: &BoolExpr) -> bool {
+    match bool_expr {
+      BoolExpr::StringEq(a, b) | BoolExpr::StringNeq(a, b) => self.string(a) || self.string(b),
+      BoolExpr::IntCmp(a, _, b) => self.int(a) || self.int(b),
+      BoolExpr::CardSetEq(a, b) | BoolExpr::CardSetNeq(a, b) => self.card_set(a) || self.card_set(b),
+      BoolExpr::CardSetIsEmpty(card_set) | BoolExpr::CardSetIsNotEmpty(card_set) => self.card_set(card_set),
+      BoolExpr::PlayerEq(a, b) | BoolExpr::PlayerNeq(a, b) => self.player(a) || self.player(b),
+      BoolExpr::TeamEq(a, b) | BoolExpr::TeamNeq(a, b) => self.team(a) || self.team(b),
+      BoolExpr::And(a, b) | BoolExpr::Or(a, b) => self.bool(a) || self.bool(b),
+      BoolExpr::Not(bool_expr) => self.bool(bool_expr),
+      BoolExpr::OutOfStagePlayer(player) | BoolExpr::OutOfGamePlayer(player) => self.player(player),
+      BoolExpr::OutOfStageCollection(players) | BoolExpr::OutOfGameCollection(players) => self.players(players),
+    }
+  }
+
+  fn int(&self, int: &IntExpr) -> bool {
+    match int {
+      IntExpr::StageRoundCounter => true,
+      IntExpr::Int(_) | IntExpr::Memory(_) | IntExpr::Demanded => false,
+      IntExpr::IntOp(a, _, b) => self.int(a) || self.int(b),
+      IntExpr::IntCollectionAt(ints, index) => self.ints(ints) || self.int(index),
+      IntExpr::SizeOf(collection) => self.collection(collection),
+      IntExpr::SumOfIntCollection(ints)
+        | IntExpr::MinIntCollection(ints)
+        | IntExpr::MaxIntCollection(ints) => self.ints(ints),
+      IntExpr::SumOfCardSet(card_set, _)
+        | IntExpr::MinOf(card_set, _)
+        | IntExpr::MaxOf(card_set, _) => self.card_set(card_set),
+    }
+  }
+
+  fn ints(&self, ints: &IntCollection) -> bool {
+    ints.ints.iter().any(|int| self.int(int))
+  }
+
+  fn string(&self, string: &StringExpr) -> bool {
+    match string {
+      StringExpr::ID(_) | StringExpr::Demanded => false,
+      StringExpr::KeyOf(_, card_position) => self.card_position(card_position),
+      StringExpr::StringCollectionAt(strings, index) => {
+        strings.strings.iter().any(|string| self.string(string)) || self.int(index)
+      },
+    }
+  }
+
+  fn collection(&self, collection: &Collection) -> bool {
+    match collection {
+      Collection::IntCollection(ints) => self.ints(ints),
+      Collection::StringCollection(strings) => strings.strings.iter().any(|string| self.string(string)),
+      Collection::LocationCollection(_) => false,
+      Collection::PlayerCollection(players) => self.players(players),
+      Collection::TeamCollection(teams) => self.teams(teams),
+      Collection::CardSet(card_set) => self.card_set(card_set),
+    }
+  }
+
+  fn player(&self, player: &PlayerExpr) -> bool {
+    match player {
+      PlayerExpr::Turnorder(int) => self.int(int),
+      PlayerExpr::OwnerOf(card_position) => self.card_position(card_position),
+      _ => false,
+    }
+  }
+
+  fn players(&self, players: &PlayerCollection) -> bool {
+    match players {
+      PlayerCollection::Player(players) => players.iter().any(|player| self.player(player)),
+      _ => false,
+    }
+  }
+
+  fn team(&self, team: &TeamExpr) -> bool {
+    match team {
+      TeamExpr::TeamName(_) => false,
+      TeamExpr::TeamOf(player) => self.player(player),
+    }
+  }
+
+  fn teams(&self, teams: &TeamCollection) -> bool {
+    match teams {
+      TeamCollection::Team(teams) => teams.iter().any(|team| self.team(team)),
+      TeamCollection::OtherTeams => false,
+    }
+  }
+
+  fn quantity(&self, quantity: &Quantity) -> bool {
+    match quantity {
+      Quantity::Int(int) => self.int(int),
+      Quantity::Quantifier(_) => false,
+      Quantity::IntRange(
+        IntRange::Eq(int)
+          | IntRange::Neq(int)
+          | IntRange::Gt(int)
+          | IntRange::Lt(int)
+          | IntRange::Ge(int)
+          | IntRange::Le(int)
+      ) => self.int(int),
+    }
+  }
+
+  fn card_position(&self, card_position: &CardPosition) -> bool {
+    match card_position {
+      CardPosition::At(_, int) => self.int(int),
+      CardPosition::Top(_) | CardPosition::Bottom(_) | CardPosition::Demanded => false,
+      CardPosition::MaxPrec(card_set, _)
+        | CardPosition::MinPrec(card_set, _)
+        | CardPosition::MaxPoint(card_set, _)
+        | CardPosition::MinPoint(card_set, _) => self.card_set(card_set),
+    }
+  }
+
+  fn card_set(&self, card_set: &CardSet) -> bool {
+    match card_set {
+      CardSet::Group(group) => self.group(group),
+      CardSet::GroupOfPlayer(group, player) => self.group(group) || self.player(player),
+      CardSet::GroupOfPlayerCollection(group, players) => self.group(group) || self.players(players),
+    }
+  }
+
+  fn group(&self, group: &Group) -> bool {
+    match group {
+      Group::Location(_) | Group::LocationCollection(_) => false,
+      Group::LocationWhere(_, filter) | Group::LocationCollectionWhere(_, filter) => self.filter(filter),
+      Group::ComboInLocation(_, _)
+        | Group::ComboInLocationCollection(_, _)
+        | Group::NotComboInLocation(_, _)
+        | Group::NotComboInLocationCollection(_, _) => self.combos,
+      Group::CardPosition(card_position) => self.card_position(card_position),
+    }
+  }
+
+  fn filter(&self, filter: &FilterExpr) -> bool {
+    match filter {
+      FilterExpr::Same(_)
+        | FilterExpr::Distinct(_)
+        | FilterExpr::Adjacent(_, _)
+        | FilterExpr::Higher(_, _)
+        | FilterExpr::Lower(_, _) => false,
+      FilterExpr::SizeEq(int)
+        | FilterExpr::SizeNeq(int)
+        | FilterExpr::SizeGt(int)
+        | FilterExpr::SizeLt(int)
+        | FilterExpr::SizeGe(int)
+        | FilterExpr::SizeLe(int) => self.int(int),
+      FilterExpr::KeyEq(_, string) | FilterExpr::KeyNeq(_, string) => self.string(string),
+      FilterExpr::Combo(_) | FilterExpr::NotCombo(_) => self.combos,
+      FilterExpr::And(a, b) | FilterExpr::Or(a, b) => self.filter(a) || self.filter(b),
+    }
+  }
+
+  fn token_loc(&self, token_loc: &TokenLocExpr) -> bool {
+    match token_loc {
+      TokenLocExpr::Location(_) | TokenLocExpr::LocationCollection(_) => false,
+      TokenLocExpr::LocationPlayer(_, player) | TokenLocExpr::LocationCollectionPlayer(_, player) => {
+        self.player(player)
+      },
+      TokenLocExpr::LocationPlayerCollection(_, players)
+        | TokenLocExpr::LocationCollectionPlayerCollection(_, players) => self.players(players),
+    }
+  }
+}
+
+/// Errors of rules that can not be executed in the current state, rather than of a faulty game.
+fn is_unsatisfiable(err: &RuntimeError) -> bool {
+  matches!(
+    err,
+    RuntimeError::NoTransition(_)
+      | RuntimeError::NoLegalAnswer
+      | RuntimeError::NoValidBid
+      | RuntimeError::NotEnoughCards { .. }
+      | RuntimeError::NotEnoughTokens { .. }
+      | RuntimeError::InvalidQuantity
+  )
+}
@@ -0,0 +1,48 @@
+// Fixtures and helpers shared by the runtime tests.
+#![allow(dead_code)]
+
+use syn::parse_str;
+
+use ast::ast::*;
+
+use runtime::error::*;
+use runtime::interpreter::*;
+use runtime::state::*;
+
+/// Three players with a hand each, a stock of eight cards and a discard pile.
+pub const SETUP: &str = "
+  players: (P1, P2, P3);
+  turnorder: (P1, P2, P3);
+  location hand on players all;
+  location (stock, discard) on table;
+  card on stock:
+    Rank(Two, Three, Four, Five)
+      for Suite(Hearts, Spades);
+";
+
+pub fn game(source: &str) -> Game {
+  parse_str(source).unwrap()
+}
+
+pub fn interpreter(source: &str) -> Interpreter {
+  Interpreter::from_game(game(source)).unwrap()
+}
+
+/// Plays a game without inputs to the end.
+pub fn run(source: &str) -> GameState {
+  let mut interpreter = interpreter(source);
+
+  assert_eq!(interpreter.run().unwrap(), Step::Finished);
+
+  interpreter.state
+}
+
+pub fn cards(state: &GameState, location: &str, player: Option<&str>) -> Vec<CardId> {
+  state.locations[state.resolve_location(location, player).unwrap()].cards.clone()
+}
+
+pub fn player(state: &GameState, player: &str) -> Result<String, RuntimeError> {
+  let player: PlayerExpr = parse_str(player).unwrap();
+
+  state.eval_player(&player)
+}
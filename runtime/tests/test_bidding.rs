@@ -0,0 +1,99 @@
+mod common;
+
+mod test {
+
+  use runtime::bidding::*;
+  use runtime::error::*;
+  use runtime::input::*;
+  use runtime::interpreter::*;
+
+  use crate::common::*;
+
+  const MEMORIES: &str = "
+    memory Bid on all;
+    memory Highest 0 on table;
+  ";
+
+  fn bid_range(interpreter: &mut Interpreter) -> BidRange {
+    match interpreter.run().unwrap() {
+      Step::Input(InputRequest { options: Options::Bid(range), .. }) => range,
+      step => panic!("expected a bid, got {:?}", step),
+    }
+  }
+
+  #[test]
+  fn test_raising_bids() {
+    let mut interpreter = interpreter(
+      &format!("{}{}{}", SETUP, MEMORIES, "
+        bid range(> memory Highest) on Bid;
+        Highest is memory Bid;
+        cycle to next;
+        bid range(> memory Highest) on Bid;
+        Highest is memory Bid;
+      ")
+    );
+
+    let range = bid_range(&mut interpreter);
+    assert_eq!(range, BidRange { min: 1, max: None, except: None });
+    interpreter.answer(Answer::Int(3)).unwrap();
+
+    // the second bid has to beat the first one
+    let range = bid_range(&mut interpreter);
+    assert_eq!(range.min, 4);
+    assert_eq!(interpreter.answer(Answer::Int(3)), Err(RuntimeError::InvalidBid(3)));
+    interpreter.answer(Answer::Int(5)).unwrap();
+
+    assert_eq!(interpreter.run().unwrap(), Step::Finished);
+
+    let state = &interpreter.state;
+    assert_eq!(state.memory_int("Bid", Some("P1")), Ok(3));
+    assert_eq!(state.memory_int("Bid", Some("P2")), Ok(5));
+    assert_eq!(state.memory_int("Highest", None), Ok(5));
+    assert_eq!(state.bid_of("P2"), Some(5));
+    assert_eq!(state.bid_of("P3"), None);
+  }
+
+  #[test]
+  fn test_bounded_bids() {
+    let mut interpreter = interpreter(
+      &format!("{}{}{}", SETUP, MEMORIES, "
+        bid range(!= 2);
+        bid range(<= 3);
+      ")
+    );
+
+    assert_eq!(bid_range(&mut interpreter), BidRange { min: 0, max: None, except: Some(2) });
+    assert_eq!(interpreter.answer(Answer::Int(2)), Err(RuntimeError::InvalidBid(2)));
+    assert_eq!(interpreter.answer(Answer::Amount(1)), Err(RuntimeError::InvalidAnswer));
+    interpreter.answer(Answer::Int(0)).unwrap();
+
+    assert_eq!(bid_range(&mut interpreter), BidRange { min: 0, max: Some(3), except: None });
+    assert_eq!(interpreter.answer(Answer::Int(4)), Err(RuntimeError::InvalidBid(4)));
+    assert_eq!(interpreter.answer(Answer::Int(-1)), Err(RuntimeError::InvalidBid(-1)));
+  }
+
+  #[test]
+  fn test_fixed_bids() {
+    let mut interpreter = interpreter(
+      &format!("{}{}{}", SETUP, MEMORIES, "
+        bid 2 on Bid;
+        bid range(< 1);
+      ")
+    );
+
+    // a single allowed bid is made without asking
+    assert_eq!(interpreter.run().unwrap(), Step::Finished);
+    assert_eq!(interpreter.state.bid_of("P1"), Some(0));
+    assert_eq!(interpreter.state.memory_int("Bid", Some("P1")), Ok(2));
+  }
+
+  #[test]
+  fn test_no_valid_bid() {
+    let mut interpreter = interpreter(
+      &format!("{}{}{}", SETUP, MEMORIES, "
+        bid range(< 0);
+      ")
+    );
+    assert_eq!(interpreter.run(), Err(RuntimeError::NoValidBid));
+  }
+}
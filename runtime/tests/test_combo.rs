@@ -0,0 +1,166 @@
+mod common;
+
+mod test {
+
+  use syn::parse_str;
+
+  use ast::ast::*;
+
+  use runtime::error::*;
+  use runtime::state::*;
+
+  use crate::common::*;
+
+  // hand: Two, Three and Four of Hearts, Four of Spades and Clubs, Nine of Spades
+  const SETUP: &str = "
+    players: (P1);
+    turnorder: (P1);
+    location hand on table;
+    card on hand: Rank(Two, Three, Four) for Suite(Hearts);
+    card on hand: Rank(Four) for Suite(Spades, Clubs);
+    card on hand: Rank(Nine) for Suite(Spades);
+    precedence RankOrder on Rank(Ace, Two, Three, Four, Five, Six, Seven, Eight, Nine);
+    combo Sequence where ((size >= 3 and same Suite) and adjacent Rank using RankOrder);
+    combo Set where ((size >= 3 and distinct Suite) and same Rank);
+    combo Deadwood where (not Sequence and not Set);
+  ";
+
+  fn hand(state: &GameState) -> Vec<CardId> {
+    state.locations[state.resolve_location("hand", None).unwrap()].cards.clone()
+  }
+
+  fn filter(filter: &str) -> FilterExpr {
+    parse_str(filter).unwrap()
+  }
+
+  #[test]
+  fn test_subsets() {
+    let state = run(SETUP);
+    let hand = hand(&state);
+
+    assert_eq!(state.combo_subsets(&hand, &filter("Sequence")).unwrap(), vec![vec![0, 1, 2]]);
+    assert_eq!(state.combo_subsets(&hand, &filter("Set")).unwrap(), vec![vec![2, 3, 4]]);
+
+    // every pair of fours
+    assert_eq!(
+      state.combo_subsets(&hand, &filter("(size == 2 and same Rank)")).unwrap(),
+      vec![vec![2, 3], vec![2, 4], vec![3, 4]]
+    );
+
+    // runs of any length, single cards included
+    assert_eq!(
+      state.combo_subsets(&hand, &filter("(same Suite and adjacent Rank using RankOrder)")).unwrap().len(),
+      9
+    );
+  }
+
+  #[test]
+  fn test_matches() {
+    let state = run(SETUP);
+
+    assert!(state.matches(&[0, 1, 2], &filter("Sequence")).unwrap());
+    assert!(!state.matches(&[0, 1, 5], &filter("Sequence")).unwrap());
+    assert!(state.matches(&[4, 3, 2], &filter("Set")).unwrap());
+    assert!(state.matches(&[2, 3], &filter("(Key(Rank == Four) or size > 5)")).unwrap());
+    assert!(!state.matches(&[1, 2], &filter("Key(Rank == Four)")).unwrap());
+    assert!(state.matches(&[0, 1, 2], &filter("higher Rank using RankOrder")).unwrap());
+    assert!(state.matches(&[2, 1], &filter("lower Rank using RankOrder")).unwrap());
+  }
+
+  #[test]
+  fn test_not_combo() {
+    let state = run(SETUP);
+    let hand = hand(&state);
+
+    assert_eq!(state.combo_members(&hand, &filter("Deadwood")).unwrap(), vec![5]);
+    assert_eq!(state.combo_members(&hand, &filter("not Set")).unwrap(), vec![0, 1, 5]);
+  }
+
+  #[test]
+  fn test_groups() {
+    let state = run(SETUP);
+
+    let int: IntExpr = parse_str("size of cards Set in hand").unwrap();
+    assert_eq!(state.eval_int(&int), Ok(3));
+
+    let int: IntExpr = parse_str("size of cards Set not in hand").unwrap();
+    assert_eq!(state.eval_int(&int), Ok(3));
+
+    let int: IntExpr = parse_str("size of cards hand where Key(Suite == Spades)").unwrap();
+    assert_eq!(state.eval_int(&int), Ok(2));
+
+    let int: IntExpr = parse_str("size of cards Deadwood in hand").unwrap();
+    assert_eq!(state.eval_int(&int), Ok(1));
+  }
+
+  #[test]
+  fn test_members_of_matching_subsets() {
+    let state = run(SETUP);
+    let hand = hand(&state);
+
+    // members are computed without the subsets where possible, they have to agree with them
+    for filter in [
+      "Sequence",
+      "Set",
+      "Deadwood",
+      "(size >= 2 and same Rank)",
+      "(size == 3 and distinct Suite)",
+      "(size > 2 and higher Rank using RankOrder)",
+      "(size >= 2 and lower Rank using RankOrder)",
+      "(size >= 4 and adjacent Rank using RankOrder)",
+      "(size != 1 and Key(Suite != Hearts))",
+      "(size <= 1 and size >= 2)",
+      "(same Suite or size >= 5)",
+    ] {
+      let filter = self::filter(filter);
+      let subsets = state.combo_subsets(&hand, &filter).unwrap();
+      let members = hand
+        .iter()
+        .filter(|card| subsets.iter().any(|subset| subset.contains(card)))
+        .copied()
+        .collect::<Vec<_>>();
+
+      assert_eq!(state.combo_members(&hand, &filter).unwrap(), members, "{:?}", filter);
+    }
+  }
+
+  #[test]
+  fn test_members_of_a_full_deck() {
+    let state = run("
+      players: (P1);
+      turnorder: (P1);
+      location (stock, discard) on table;
+      card on stock:
+        Rank(Two, Three, Four, Five, Six, Seven, Eight, Nine, Ten, Jack, Queen, King, Ace)
+          for Suite(Hearts, Spades, Diamonds, Clubs);
+      precedence RankOrder on Rank(Two, Three, Four, Five, Six, Seven, Eight, Nine, Ten, Jack, Queen, King, Ace);
+      combo Sequence where ((size >= 3 and same Suite) and adjacent Rank using RankOrder);
+      move all from stock where size >= 3 face up to discard;
+    ");
+
+    let discard = state.locations[state.resolve_location("discard", None).unwrap()].cards.clone();
+    assert_eq!(discard.len(), 52);
+
+    let int: IntExpr = parse_str("size of cards Sequence in discard").unwrap();
+    assert_eq!(state.eval_int(&int), Ok(52));
+  }
+
+  #[test]
+  fn test_unknown_and_recursive_combo() {
+    let state = run(
+      &format!("{}{}", SETUP, "
+        combo Loop where (size >= 1 and Loop);
+      ")
+    );
+    let hand = hand(&state);
+
+    assert_eq!(
+      state.combo_members(&hand, &filter("Missing")),
+      Err(RuntimeError::UnknownCombo(String::from("Missing")))
+    );
+    assert_eq!(
+      state.combo_members(&hand, &filter("Loop")),
+      Err(RuntimeError::RecursiveCombo(String::from("Loop")))
+    );
+  }
+}
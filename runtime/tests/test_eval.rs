@@ -0,0 +1,122 @@
+mod common;
+
+mod test {
+
+  use syn::parse_str;
+
+  use ast::ast::*;
+
+  use runtime::error::*;
+  use runtime::state::*;
+
+  use crate::common::*;
+
+  fn eval(state: &GameState, int: &str) -> Result<i32, RuntimeError> {
+    let int: IntExpr = parse_str(int).unwrap();
+
+    state.eval_int(&int)
+  }
+
+  const SETUP: &str = "
+    players: (P1, P2, P3);
+    turnorder: (P1, P2, P3);
+    location hand on players all;
+    location stock on table;
+    card on stock:
+      Rank(Two, Three, King, Ace)
+        for Suite(Hearts, Spades);
+    pointmap Values on Rank(Two: 2, Three: 3, King: 10, Ace: (10 + 1));
+  ";
+
+  #[test]
+  fn test_arithmetic() {
+    let state = run(SETUP);
+
+    assert_eq!(eval(&state, "((2 + 3) * 4)"), Ok(20));
+    assert_eq!(eval(&state, "(7 / 2)"), Ok(3));
+    assert_eq!(eval(&state, "(7 % 2)"), Ok(1));
+    assert_eq!(eval(&state, "(7 - (2 * 5))"), Ok(-3));
+  }
+
+  #[test]
+  fn test_division_by_zero() {
+    let state = run(SETUP);
+
+    assert_eq!(eval(&state, "(7 / 0)"), Err(RuntimeError::DivisionByZero));
+    assert_eq!(eval(&state, "(7 % (2 - 2))"), Err(RuntimeError::DivisionByZero));
+  }
+
+  #[test]
+  fn test_overflow() {
+    let state = run(SETUP);
+
+    assert_eq!(eval(&state, "(2147483647 + 1)"), Err(RuntimeError::Overflow));
+  }
+
+  #[test]
+  fn test_int_collections() {
+    let state = run(SETUP);
+
+    assert_eq!(eval(&state, "sum(1, 2, 3)"), Ok(6));
+    assert_eq!(eval(&state, "min(4, 2, 3)"), Ok(2));
+    assert_eq!(eval(&state, "max(4, 2, 3)"), Ok(4));
+    assert_eq!(eval(&state, "(4, 2, 3)[2]"), Ok(3));
+    assert_eq!(
+      eval(&state, "(4, 2, 3)[3]"),
+      Err(RuntimeError::IndexOutOfRange { index: 3, len: 3 })
+    );
+  }
+
+  #[test]
+  fn test_size_of() {
+    let state = run(SETUP);
+
+    assert_eq!(eval(&state, "size of ints(4, 2, 3)"), Ok(3));
+    assert_eq!(eval(&state, "size of players others"), Ok(2));
+    assert_eq!(eval(&state, "size of cards stock"), Ok(8));
+    assert_eq!(eval(&state, "size of cards hand"), Ok(0));
+  }
+
+  #[test]
+  fn test_point_maps() {
+    let state = run(SETUP);
+
+    // 2 * (2 + 3 + 10 + 11)
+    assert_eq!(eval(&state, "sum of stock using Values"), Ok(52));
+    assert_eq!(eval(&state, "min of stock using Values"), Ok(2));
+    assert_eq!(eval(&state, "max of stock using Values"), Ok(11));
+    assert_eq!(eval(&state, "sum of hand using Values"), Ok(0));
+    assert_eq!(eval(&state, "max of hand using Values"), Err(RuntimeError::EmptyCollection));
+    assert_eq!(
+      eval(&state, "sum of stock using Missing"),
+      Err(RuntimeError::UnknownPointMap(String::from("Missing")))
+    );
+  }
+
+  #[test]
+  fn test_card_position_by_points() {
+    let state = run(SETUP);
+
+    let position: CardPosition = parse_str("max(stock) using point(Values)").unwrap();
+    let card = state.eval_card(&position).unwrap();
+    assert_eq!(state.card(card).unwrap().value("Rank"), Some("Ace"));
+    assert_eq!(state.card(card).unwrap().value("Suite"), Some("Hearts"));
+  }
+
+  #[test]
+  fn test_stage_round_counter() {
+    let state = run(
+      &format!("{}{}", SETUP, "
+        stage Play for current until(end) {
+          if (stageroundcounter == 3) {
+            end game with winner current;
+          }
+          cycle to next;
+        }
+      ")
+    );
+
+    assert_eq!(state.winners, vec![String::from("P1")]);
+    assert_eq!(eval(&state, "stageroundcounter"), Ok(3));
+  }
+}
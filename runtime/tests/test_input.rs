@@ -0,0 +1,189 @@
+mod common;
+
+mod test {
+
+  use runtime::error::*;
+  use runtime::input::*;
+  use runtime::interpreter::*;
+
+  use crate::common::*;
+
+  fn request(interpreter: &mut Interpreter) -> InputRequest {
+    match interpreter.run().unwrap() {
+      Step::Input(request) => request,
+      step => panic!("expected an input, got {:?}", step),
+    }
+  }
+
+  #[test]
+  fn test_demand_card() {
+    let mut game = interpreter(
+      &format!("{}{}", SETUP, "
+        move 3 from stock private to hand;
+        demand top(hand);
+        move demanded face up to discard;
+      ")
+    );
+
+    let request = request(&mut game);
+    assert_eq!(request.player, "P1");
+    assert_eq!(request.options, Options::Card(vec![CardChoice::Card(0), CardChoice::Card(1), CardChoice::Card(2)]));
+    assert_eq!(request.answer_type(), AnswerType::Card);
+
+    assert_eq!(game.answer(Answer::Card(CardChoice::Card(3))), Err(RuntimeError::InvalidAnswer));
+    assert_eq!(game.answer(Answer::Int(1)), Err(RuntimeError::InvalidAnswer));
+    game.answer(Answer::Card(CardChoice::Card(1))).unwrap();
+
+    assert_eq!(game.run().unwrap(), Step::Finished);
+    assert_eq!(game.state.demanded, Some(Demanded::Card(1)));
+    assert_eq!(cards(&game.state, "discard", None), vec![1]);
+    assert_eq!(cards(&game.state, "hand", Some("P1")), vec![0, 2]);
+  }
+
+  #[test]
+  fn test_demand_hidden_card() {
+    let mut game = interpreter(
+      &format!("{}{}", SETUP, "
+        flip top(stock) to face up;
+        demand top(stock);
+        move demanded face up to discard;
+      ")
+    );
+
+    // only the face up card is offered by its id, the others by their place in the stock
+    let options = match request(&mut game).options {
+      Options::Card(options) => options,
+      options => panic!("expected cards, got {:?}", options),
+    };
+    let stock = cards(&game.state, "stock", None);
+    let mut expected = vec![CardChoice::Card(stock[0])];
+    expected.extend((1..8).map(CardChoice::Hidden));
+    assert_eq!(options, expected);
+
+    assert_eq!(game.answer(Answer::Card(CardChoice::Card(stock[2]))), Err(RuntimeError::InvalidAnswer));
+    assert_eq!(game.answer(Answer::Card(CardChoice::Hidden(8))), Err(RuntimeError::InvalidAnswer));
+    game.answer(Answer::Card(CardChoice::Hidden(2))).unwrap();
+
+    assert_eq!(game.run().unwrap(), Step::Finished);
+    assert_eq!(game.state.demanded, Some(Demanded::Card(stock[2])));
+    assert_eq!(cards(&game.state, "discard", None), vec![stock[2]]);
+  }
+
+  #[test]
+  fn test_demand_string() {
+    let mut game = interpreter(
+      &format!("{}{}", SETUP, "
+        demand Suite;
+        demand Trump;
+      ")
+    );
+
+    let request = request(&mut game);
+    assert_eq!(request.options, Options::String(vec![String::from("Hearts"), String::from("Spades")]));
+    assert_eq!(game.answer(Answer::String(String::from("Clubs"))), Err(RuntimeError::InvalidAnswer));
+    game.answer(Answer::String(String::from("Spades"))).unwrap();
+
+    // a string that is not a key has only one option and is answered right away
+    assert_eq!(game.run().unwrap(), Step::Finished);
+    assert_eq!(game.state.demanded, Some(Demanded::String(String::from("Trump"))));
+
+    // `X is demanded` is an int rule, it does not store strings
+    let mut game = interpreter(
+      &format!("{}{}", SETUP, "
+        memory Called on table;
+        demand Suite;
+        Called is demanded;
+      ")
+    );
+    assert!(matches!(game.run().unwrap(), Step::Input(_)));
+    game.answer(Answer::String(String::from("Spades"))).unwrap();
+    assert_eq!(game.run(), Err(RuntimeError::NotDemanded(String::from("int"))));
+    assert_eq!(game.state.memory("Called", None).unwrap().value, None);
+  }
+
+  #[test]
+  fn test_demand_int() {
+    let mut game = interpreter(
+      &format!("{}{}", SETUP, "
+        memory Called on table;
+        demand 3;
+        Called is demanded;
+        if (demanded == 2) {
+          cycle to next;
+        }
+        demand 0;
+      ")
+    );
+
+    // both bounds are included
+    let options = request(&mut game).options;
+    assert_eq!(options, Options::Int { min: 0, max: 3 });
+    assert!(options.accepts(&Answer::Int(0)) && options.accepts(&Answer::Int(3)));
+    assert_eq!(game.answer(Answer::Int(-1)), Err(RuntimeError::InvalidAnswer));
+    assert_eq!(game.answer(Answer::Int(4)), Err(RuntimeError::InvalidAnswer));
+    game.answer(Answer::Int(2)).unwrap();
+
+    assert_eq!(game.run().unwrap(), Step::Finished);
+    assert_eq!(game.state.current_player().unwrap(), "P2");
+    assert_eq!(game.state.demanded, Some(Demanded::Int(0)));
+    assert_eq!(game.state.memory_int("Called", None), Ok(2));
+  }
+
+  #[test]
+  fn test_demand_bounds() {
+    // a negative int has no legal answer
+    let mut game = interpreter(
+      &format!("{}{}", SETUP, "
+        demand (0 - 1);
+      ")
+    );
+    assert_eq!(game.run(), Err(RuntimeError::NoLegalAnswer));
+
+    // the values of a key are offered wherever the cards are
+    let mut game = interpreter(
+      &format!("{}{}", SETUP, "
+        move all from stock face up to discard;
+        demand Rank;
+      ")
+    );
+    assert_eq!(
+      request(&mut game).options,
+      Options::String(["Two", "Three", "Four", "Five"].iter().map(|rank| rank.to_string()).collect())
+    );
+  }
+
+  #[test]
+  fn test_demand_without_answer() {
+    let mut game = interpreter(
+      &format!("{}{}", SETUP, "
+        demand top(hand);
+      ")
+    );
+
+    assert_eq!(game.run(), Err(RuntimeError::NoLegalAnswer));
+  }
+
+  #[test]
+  fn test_optional_input() {
+    let mut game = interpreter(
+      &format!("{}{}", SETUP, "
+        optional {
+          cycle to next;
+        }
+      ")
+    );
+
+    let (enter, skip) = match request(&mut game).options {
+      Options::Optional { enter, skip } => (enter, skip),
+      options => panic!("expected an optional, got {:?}", options),
+    };
+    assert_ne!(enter, skip);
+
+    game.answer(Answer::Transition(skip)).unwrap();
+    assert_eq!(game.pending_input(), Ok(None));
+    assert_eq!(game.answer(Answer::Int(0)), Err(RuntimeError::NoPendingInput));
+
+    assert_eq!(game.run().unwrap(), Step::Finished);
+    assert_eq!(game.state.current_player().unwrap(), "P1");
+  }
+}
@@ -0,0 +1,293 @@
+mod common;
+
+mod test {
+
+  use quote::format_ident;
+  use syn::parse_str;
+
+  use ast::ast::*;
+  use ir::fsm::*;
+
+  use runtime::error::*;
+  use runtime::input::*;
+  use runtime::interpreter::*;
+  use runtime::state::*;
+
+  use crate::common::*;
+
+  #[test]
+  fn test_creation_rules() {
+    let mut interpreter = interpreter(
+      "
+        players: (P1, P2, P3);
+        team A: (P1, P3);
+        turnorder: (P2, P3, P1);
+      "
+    );
+
+    assert_eq!(interpreter.run().unwrap(), Step::Finished);
+
+    let state = &interpreter.state;
+    assert_eq!(state.players.len(), 3);
+    assert_eq!(state.team_of("P3").unwrap().name, "A");
+    assert_eq!(state.current_player().unwrap(), "P2");
+  }
+
+  #[test]
+  fn test_stage_repetitions() {
+    let mut interpreter = interpreter(
+      "
+        players: (P1, P2, P3);
+        turnorder: (P1, P2, P3);
+
+        stage Play for current until(4 times) {
+          cycle to next;
+        }
+      "
+    );
+
+    assert_eq!(interpreter.run().unwrap(), Step::Finished);
+
+    // four rounds with three players end at P2
+    assert_eq!(interpreter.state.current_player().unwrap(), "P2");
+    assert!(interpreter.state.stages.is_empty());
+  }
+
+  #[test]
+  fn test_end_stage() {
+    let mut interpreter = interpreter(
+      "
+        players: (P1, P2, P3);
+        turnorder: (P1, P2, P3);
+
+        stage Play for current until(end) {
+          if (stageroundcounter == 2) {
+            end stage;
+          }
+          cycle to next;
+        }
+        end game with winner current;
+      "
+    );
+
+    assert_eq!(interpreter.run().unwrap(), Step::Finished);
+
+    // the third round ends the stage before the cycle
+    assert_eq!(interpreter.state.winners, vec![String::from("P3")]);
+    assert!(interpreter.state.stages.is_empty());
+  }
+
+  #[test]
+  fn test_stage_until_bool() {
+    let mut interpreter = interpreter(
+      "
+        players: (P1, P2, P3);
+        turnorder: (P1, P2, P3);
+
+        stage Play for current until(P3 out of stage) {
+          if (stageroundcounter == 2) {
+            set current out of stage;
+          }
+        }
+      "
+    );
+
+    assert_eq!(interpreter.run().unwrap(), Step::Finished);
+
+    // every round passes the turn on its own, P3 sets themselves out in the third round
+    assert_eq!(interpreter.state.current_player().unwrap(), "P1");
+  }
+
+  #[test]
+  fn test_stage_player() {
+    let mut interpreter = interpreter(
+      "
+        players: (P1, P2, P3);
+        turnorder: (P1, P2, P3);
+        memory Turns on all;
+
+        stage Play for P3 until(1 times) {
+          Turns is 1;
+        }
+      "
+    );
+
+    assert_eq!(interpreter.run().unwrap(), Step::Finished);
+
+    // the stage starts with its own player instead of the first one of the turnorder
+    let set = interpreter.state.memories
+      .iter()
+      .filter(|memory| memory.value.is_some())
+      .map(|memory| memory.owner.clone())
+      .collect::<Vec<_>>();
+    assert_eq!(set, vec![Owner::Player(String::from("P3"))]);
+  }
+
+  #[test]
+  fn test_round_end_passes_turn() {
+    let mut interpreter = interpreter(
+      "
+        players: (P1, P2, P3);
+        turnorder: (P1, P2, P3);
+        memory Turns on all;
+
+        stage Play for current until(2 times) {
+          if (stageroundcounter == 1) {
+            cycle to P1;
+          }
+          Turns is 1;
+        }
+      "
+    );
+
+    assert_eq!(interpreter.run().unwrap(), Step::Finished);
+
+    // the first round passes the turn at its end, the second one passed it by the cycle already
+    let set = interpreter.state.memories
+      .iter()
+      .filter(|memory| memory.value.is_some())
+      .map(|memory| memory.owner.clone())
+      .collect::<Vec<_>>();
+    assert_eq!(set, vec![Owner::Player(String::from("P1"))]);
+    assert_eq!(interpreter.state.current_player().unwrap(), "P1");
+  }
+
+  #[test]
+  fn test_choice_decision() {
+    let mut interpreter = interpreter(
+      "
+        players: (P1, P2);
+        turnorder: (P1, P2);
+
+        choose {
+          cycle to P1;
+          or
+          cycle to P2;
+        }
+      "
+    );
+
+    let options = match interpreter.run().unwrap() {
+      Step::Input(InputRequest { options: Options::Choice(options), .. }) => options,
+      step => panic!("expected a decision, got {:?}", step),
+    };
+    assert_eq!(options.len(), 2);
+
+    interpreter.answer(Answer::Transition(options[1])).unwrap();
+
+    assert_eq!(interpreter.run().unwrap(), Step::Finished);
+    assert_eq!(interpreter.state.current_player().unwrap(), "P2");
+  }
+
+  #[test]
+  fn test_invalid_decision() {
+    let mut interpreter = interpreter(
+      "
+        players: (P1, P2);
+        turnorder: (P1, P2);
+
+        optional {
+          cycle to next;
+        }
+      "
+    );
+
+    // the first transition creates the players
+    assert!(matches!(interpreter.run().unwrap(), Step::Input(InputRequest { options: Options::Optional { .. }, .. })));
+    assert_eq!(interpreter.answer(Answer::Transition(1)), Err(RuntimeError::InvalidDecision(1)));
+    assert_eq!(interpreter.answer(Answer::Int(1)), Err(RuntimeError::InvalidAnswer));
+  }
+
+  #[test]
+  fn test_end_game_with_winner() {
+    let mut interpreter = interpreter(
+      "
+        players: (P1, P2);
+        turnorder: (P1, P2);
+        end game with winner P2;
+        cycle to next;
+      "
+    );
+
+    assert_eq!(interpreter.run().unwrap(), Step::Finished);
+    assert_eq!(interpreter.state.winners, vec![String::from("P2")]);
+    assert_eq!(interpreter.state.current_player().unwrap(), "P1");
+    assert!(interpreter.fsm().is_goal(interpreter.current_state()));
+  }
+
+  #[test]
+  fn test_unknown_player() {
+    let mut interpreter = interpreter(
+      "
+        players: (P1, P2);
+        turnorder: (P1, P3);
+      "
+    );
+
+    assert_eq!(interpreter.run(), Err(RuntimeError::UnknownPlayer(String::from("P3"))));
+  }
+
+  #[test]
+  fn test_invalid_game() {
+    let game: Game = parse_str(
+      "
+        players: (P1, P2);
+        turnorder: (P1, P2);
+        end stage;
+      "
+    ).unwrap();
+
+    assert_eq!(
+      Interpreter::from_game(game).unwrap_err(),
+      RuntimeError::InvalidGame(vec![BuildError { kind: BuildErrorKind::EndStageOutsideStage, path: vec![2] }])
+    );
+  }
+
+  #[test]
+  fn test_manual_fsm() {
+    let mut builder = FSMBuilder::default();
+    let fsm = builder.build_fsm(
+      Game {
+        flows: vec![
+          FlowComponent::Rule(Rule::CreatePlayer(vec![format_ident!("P1")]))
+        ]
+      }
+    ).unwrap();
+
+    let mut interpreter = Interpreter::new(fsm);
+
+    assert_eq!(interpreter.step().unwrap(), Step::Transition(1));
+    assert_eq!(interpreter.step().unwrap(), Step::Finished);
+    assert_eq!(interpreter.state.players.len(), 1);
+  }
+
+  #[test]
+  fn test_end_turn() {
+    let mut interpreter = interpreter(
+      "
+        players: (P1, P2, P3);
+        turnorder: (P1, P2, P3);
+        memory Turns on all;
+
+        stage Play for current until(4 times) {
+          if (stageroundcounter == 1) {
+            end turn;
+          }
+          Turns is 1;
+          cycle to next;
+        }
+      "
+    );
+
+    assert_eq!(interpreter.run().unwrap(), Step::Finished);
+
+    // P2 ends the turn of the second round before setting the memory, the turn passes on all the same
+    assert_eq!(interpreter.state.current_player().unwrap(), "P2");
+    let set = interpreter.state.memories
+      .iter()
+      .filter(|memory| memory.value.is_some())
+      .map(|memory| memory.owner.clone())
+      .collect::<Vec<_>>();
+    assert_eq!(set, vec![Owner::Player(String::from("P1")), Owner::Player(String::from("P3"))]);
+  }
+}
@@ -0,0 +1,183 @@
+mod common;
+
+mod test {
+
+  use runtime::bidding::*;
+  use runtime::input::*;
+  use runtime::interpreter::*;
+  use runtime::legal::*;
+  use runtime::rng::*;
+
+  use crate::common::*;
+
+  /// Runs until the first input and returns its legal actions.
+  fn actions(game: &mut Interpreter) -> Vec<Answer> {
+    assert!(matches!(game.run().unwrap(), Step::Input(_)));
+
+    game.legal_actions().unwrap()
+  }
+
+  /// Every legal action is accepted by the interpreter.
+  fn assert_accepted(game: &Interpreter, actions: &[Answer]) {
+    for action in actions.iter() {
+      let mut copy = game.clone();
+      assert_eq!(copy.answer(action.clone()), Ok(()), "{:?} was not accepted", action);
+    }
+  }
+
+  const MEMORIES: &str = "
+    memory Bid on all;
+  ";
+
+  #[test]
+  fn test_choice_and_optional() {
+    let mut game = interpreter(
+      &format!("{}{}{}", SETUP, MEMORIES, "
+        choose {
+          cycle to P1;
+          or
+          cycle to P2;
+          or
+          cycle to next;
+        }
+        optional {
+          cycle to next;
+        }
+      ")
+    );
+
+    let choices = actions(&mut game);
+    assert_eq!(choices.len(), 3);
+    assert!(choices.iter().all(|action| matches!(action, Answer::Transition(_))));
+    assert_accepted(&game, &choices);
+
+    game.answer(choices[1].clone()).unwrap();
+    let optional = actions(&mut game);
+    assert_eq!(optional.len(), 2);
+    assert_accepted(&game, &optional);
+  }
+
+  #[test]
+  fn test_cards_to_move() {
+    let mut game = interpreter(
+      &format!("{}{}{}", SETUP, MEMORIES, "
+        move 6 from stock face up to discard;
+        move range(>= 1) from stock face up to hand;
+      ")
+    );
+
+    let moves = actions(&mut game);
+    assert_eq!(
+      moves,
+      vec![
+        Answer::Cards(vec![CardChoice::Hidden(0)]),
+        Answer::Cards(vec![CardChoice::Hidden(1)]),
+        Answer::Cards(vec![CardChoice::Hidden(0), CardChoice::Hidden(1)]),
+      ]
+    );
+    assert_accepted(&game, &moves);
+  }
+
+  #[test]
+  fn test_hidden_cards_to_move() {
+    let mut game = interpreter(
+      &format!("{}{}{}", SETUP, MEMORIES, "
+        shuffle stock;
+        move 4 from stock face up to discard;
+        move any from stock face down to discard;
+      ")
+    );
+
+    let moves = actions(&mut game);
+    assert_eq!(moves.len(), 15);
+    assert!(moves.iter().all(|action| match action {
+      Answer::Cards(choices) => choices.iter().all(|choice| matches!(choice, CardChoice::Hidden(index) if *index < 4)),
+      _ => false,
+    }));
+    assert_accepted(&game, &moves);
+
+    let request = game.pending_input().unwrap().unwrap();
+    let mut rng = Rng::new(3);
+    for _ in 0..20 {
+      match request.options.sample(&mut rng) {
+        Some(Answer::Cards(choices)) => assert!(choices.iter().all(|choice| matches!(choice, CardChoice::Hidden(_)))),
+        answer => panic!("expected cards, got {:?}", answer),
+      }
+    }
+  }
+
+  #[test]
+  fn test_bids_and_demands() {
+    let mut game = interpreter(
+      &format!("{}{}{}", SETUP, MEMORIES, "
+        bid range(<= 3) on Bid;
+        bid range(> memory Bid) on Bid;
+        demand 2;
+      ")
+    );
+
+    let bids = actions(&mut game);
+    assert_eq!(bids, (0..=3).map(Answer::Int).collect::<Vec<_>>());
+    assert_accepted(&game, &bids);
+    game.answer(Answer::Int(2)).unwrap();
+
+    // open ranges are cut off
+    let bids = actions(&mut game);
+    assert_eq!(bids.len(), MAX_OPEN_BIDS as usize);
+    assert_eq!(bids.first(), Some(&Answer::Int(3)));
+    game.answer(Answer::Int(3)).unwrap();
+
+    assert_eq!(actions(&mut game), vec![Answer::Int(0), Answer::Int(1), Answer::Int(2)]);
+  }
+
+  #[test]
+  fn test_count_and_sample() {
+    let mut game = interpreter(
+      &format!("{}{}{}", SETUP, MEMORIES, "
+        move any from stock face up to discard;
+      ")
+    );
+
+    let request = match game.run().unwrap() {
+      Step::Input(request) => request,
+      step => panic!("expected an input, got {:?}", step),
+    };
+    assert_eq!(request.options.count(), request.options.answers().count());
+
+    let mut rng = Rng::new(7);
+    for _ in 0..20 {
+      let answer = request.options.sample(&mut rng).unwrap();
+      let mut copy = game.clone();
+      assert_eq!(copy.answer(answer.clone()), Ok(()), "{:?} was not accepted", answer);
+    }
+
+    // a full deck is counted and sampled without listing its combinations
+    let deck = Options::Cards { cards: (0..52).map(CardChoice::Hidden).collect(), counts: (0..=52).collect() };
+    assert_eq!(deck.count(), 1 << 52);
+    assert!(matches!(deck.sample(&mut rng), Some(Answer::Cards(_))));
+    assert_eq!(deck.answers().next(), Some(Answer::Cards(vec![])));
+
+    let bids = Options::Bid(BidRange { min: 2, max: Some(5), except: Some(3) });
+    assert_eq!(bids.count(), 3);
+    assert_eq!(bids.answers().collect::<Vec<_>>(), vec![Answer::Int(2), Answer::Int(4), Answer::Int(5)]);
+
+    let empty = Options::Cards { cards: vec![CardChoice::Card(1), CardChoice::Card(2)], counts: vec![3] };
+    assert_eq!(empty.count(), 0);
+    assert!(empty.is_empty());
+    assert!(!deck.is_empty());
+    assert_eq!(empty.sample(&mut rng), None);
+  }
+
+  #[test]
+  fn test_nothing_to_decide() {
+    let mut game = interpreter(
+      &format!("{}{}{}", SETUP, MEMORIES, "
+        move all from stock private to hand;
+      ")
+    );
+
+    assert_eq!(game.legal_actions(), Ok(vec![]));
+    assert_eq!(game.run().unwrap(), Step::Finished);
+    assert_eq!(game.legal_actions(), Ok(vec![]));
+  }
+}
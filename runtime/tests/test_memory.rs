@@ -0,0 +1,117 @@
+mod common;
+
+mod test {
+
+  use runtime::error::*;
+  use runtime::memory::*;
+  use runtime::state::*;
+
+  use crate::common::*;
+
+  const MEMORIES: &str = "
+    memory Tricks 0 on all;
+    memory Trump on table;
+    memory Round 1 on table;
+  ";
+
+  #[test]
+  fn test_create_memories() {
+    let state = run(&format!("{}{}", SETUP, MEMORIES));
+
+    let tricks = state.memory("Tricks", Some("P2")).unwrap();
+    assert_eq!(tricks.owner, Owner::Player(String::from("P2")));
+    assert_eq!(tricks.memory_type, Some(MemoryType::Int));
+    assert_eq!(tricks.value, Some(MemoryValue::Int(0)));
+
+    let trump = state.memory("Trump", None).unwrap();
+    assert_eq!(trump.owner, Owner::Table);
+    assert_eq!(trump.memory_type, None);
+    assert_eq!(state.memory_int("Trump", None), Err(RuntimeError::UnsetMemory(String::from("Trump"))));
+  }
+
+  #[test]
+  fn test_set_memories() {
+    let state = run(
+      &format!("{}{}{}", SETUP, MEMORIES, "
+        Trump is Hearts;
+        Round is (1 + 1);
+        Tricks is 3;
+        cycle to P3;
+        Tricks is 5;
+      ")
+    );
+
+    assert_eq!(state.memory("Trump", None).unwrap().value, Some(MemoryValue::String(String::from("Hearts"))));
+    assert_eq!(state.memory_int("Round", None), Ok(2));
+    assert_eq!(state.memory_int("Tricks", Some("P1")), Ok(3));
+    assert_eq!(state.memory_int("Tricks", Some("P2")), Ok(0));
+    assert_eq!(state.memory_int("Tricks", Some("P3")), Ok(5));
+  }
+
+  #[test]
+  fn test_collections() {
+    let state = run(
+      &format!("{}{}{}", SETUP, MEMORIES, "
+        Trump is players others;
+      ")
+    );
+
+    assert_eq!(
+      state.memory("Trump", None).unwrap().value,
+      Some(MemoryValue::Collection(CollectionValue::Players(vec![String::from("P2"), String::from("P3")])))
+    );
+  }
+
+  #[test]
+  fn test_type_mismatch() {
+    let mut game = interpreter(
+      &format!("{}{}{}", SETUP, MEMORIES, "
+        Trump is Hearts;
+        Trump is 4;
+      ")
+    );
+    assert_eq!(
+      game.run(),
+      Err(
+        RuntimeError::MemoryTypeMismatch {
+          memory: String::from("Trump"),
+          expected: MemoryType::String,
+          found: MemoryType::Int,
+        }
+      )
+    );
+
+    let mut game = interpreter(
+      &format!("{}{}{}", SETUP, MEMORIES, "
+        Tricks is ints(1, 2);
+      ")
+    );
+    assert!(matches!(game.run(), Err(RuntimeError::MemoryTypeMismatch { .. })));
+
+    let mut game = interpreter(
+      &format!("{}{}{}", SETUP, MEMORIES, "
+        Missing is 1;
+      ")
+    );
+    assert_eq!(game.run(), Err(RuntimeError::UnknownMemory(String::from("Missing"))));
+  }
+
+  #[test]
+  fn test_owner_of_memory() {
+    let state = run(
+      &format!("{}{}{}", SETUP, MEMORIES, "
+        cycle to P2;
+        Tricks is 4;
+        cycle to P3;
+        Tricks is 4;
+        winner is highest Tricks;
+      ")
+    );
+
+    // ties go to the first player in turnorder, but tied players all win
+    assert_eq!(player(&state, "owner of highest Tricks"), Ok(String::from("P2")));
+    assert_eq!(player(&state, "owner of lowest Tricks"), Ok(String::from("P1")));
+    assert_eq!(state.winners, vec![String::from("P2"), String::from("P3")]);
+    assert_eq!(player(&state, "owner of highest Round"), Err(RuntimeError::UnknownMemory(String::from("Round"))));
+  }
+}
@@ -0,0 +1,171 @@
+mod common;
+
+mod test {
+
+  use runtime::error::*;
+  use runtime::interpreter::*;
+  use runtime::input::*;
+  use runtime::state::*;
+
+  use crate::common::*;
+
+  #[test]
+  fn test_move() {
+    let state = run(
+      &format!("{}{}", SETUP, "
+        move 3 from stock face up to discard;
+        move top(discard) private to hand;
+      ")
+    );
+
+    assert_eq!(cards(&state, "stock", None), vec![3, 4, 5, 6, 7]);
+    assert_eq!(cards(&state, "discard", None), vec![1, 2]);
+    assert_eq!(cards(&state, "hand", Some("P1")), vec![0]);
+    assert_eq!(state.card(0).unwrap().status, CardStatus::Private);
+    assert_eq!(state.card(1).unwrap().status, CardStatus::FaceUp);
+  }
+
+  #[test]
+  fn test_not_enough_cards() {
+    let mut interpreter = interpreter(
+      &format!("{}{}", SETUP, "
+        move 9 from stock face up to discard;
+      ")
+    );
+
+    assert_eq!(interpreter.run(), Err(RuntimeError::NotEnoughCards { needed: 9, available: 8 }));
+  }
+
+  #[test]
+  fn test_empty_quantity() {
+    let mut interpreter = interpreter(
+      &format!("{}{}", SETUP, "
+        move range(< 0) from stock face up to discard;
+      ")
+    );
+
+    assert_eq!(interpreter.run(), Err(RuntimeError::InvalidQuantity));
+  }
+
+  #[test]
+  fn test_deal() {
+    let state = run(
+      &format!("{}{}", SETUP, "
+        deal 2 from stock private to hand of all;
+      ")
+    );
+
+    assert_eq!(cards(&state, "hand", Some("P1")), vec![3, 0]);
+    assert_eq!(cards(&state, "hand", Some("P2")), vec![4, 1]);
+    assert_eq!(cards(&state, "hand", Some("P3")), vec![5, 2]);
+    assert_eq!(cards(&state, "stock", None), vec![6, 7]);
+
+    // without a quantity every card is dealt
+    let state = run(
+      &format!("{}{}", SETUP, "
+        deal stock face down to hand of (P1, P2);
+      ")
+    );
+    assert_eq!(cards(&state, "hand", Some("P1")).len(), 4);
+    assert_eq!(cards(&state, "hand", Some("P2")).len(), 4);
+  }
+
+  #[test]
+  fn test_exchange() {
+    let state = run(
+      &format!("{}{}", SETUP, "
+        move 2 from stock private to hand;
+        exchange 1 from hand face up with stock;
+      ")
+    );
+
+    assert_eq!(cards(&state, "hand", Some("P1")), vec![2, 1]);
+    assert_eq!(cards(&state, "stock", None), vec![0, 3, 4, 5, 6, 7]);
+    assert_eq!(state.card(0).unwrap().status, CardStatus::FaceUp);
+  }
+
+  #[test]
+  fn test_free_quantity() {
+    let mut interpreter = interpreter(
+      &format!("{}{}", SETUP, "
+        move range(>= 2) from stock face up to discard;
+      ")
+    );
+
+    let (options, counts) = match interpreter.run().unwrap() {
+      Step::Input(InputRequest { player, options: Options::Cards { cards, counts } }) => {
+        assert_eq!(player, "P1");
+
+        (cards, counts)
+      },
+      step => panic!("expected a quantity, got {:?}", step),
+    };
+    // the stock is face down, its cards are offered by their place
+    assert_eq!(options, (0..8).map(CardChoice::Hidden).collect::<Vec<_>>());
+    assert_eq!(counts, vec![2, 3, 4, 5, 6, 7, 8]);
+
+    assert_eq!(interpreter.answer(Answer::Cards(vec![CardChoice::Hidden(5)])), Err(RuntimeError::InvalidQuantity));
+    assert_eq!(
+      interpreter.answer(Answer::Cards(vec![CardChoice::Hidden(5), CardChoice::Hidden(5)])),
+      Err(RuntimeError::InvalidQuantity)
+    );
+    assert_eq!(
+      interpreter.answer(Answer::Cards(vec![CardChoice::Card(5), CardChoice::Card(7)])),
+      Err(RuntimeError::InvalidQuantity)
+    );
+
+    interpreter.answer(Answer::Cards(vec![CardChoice::Hidden(5), CardChoice::Hidden(7)])).unwrap();
+    assert_eq!(interpreter.run().unwrap(), Step::Finished);
+    assert_eq!(cards(&interpreter.state, "discard", None), vec![5, 7]);
+  }
+
+  #[test]
+  fn test_free_quantity_hides_cards() {
+    let mut interpreter = interpreter(
+      &format!("{}{}", SETUP, "
+        shuffle stock;
+        move 2 from stock face up to discard;
+        move range(>= 1) from discard face up to hand;
+        move range(>= 1) from stock face down to discard;
+      ")
+    );
+
+    let options = |interpreter: &mut Interpreter| match interpreter.run().unwrap() {
+      Step::Input(InputRequest { options: Options::Cards { cards, .. }, .. }) => cards,
+      step => panic!("expected a quantity, got {:?}", step),
+    };
+
+    // the face up cards are named, their ids are no secret
+    let visible = options(&mut interpreter);
+    assert_eq!(visible, cards(&interpreter.state, "discard", None).into_iter().map(CardChoice::Card).collect::<Vec<_>>());
+    interpreter.answer(Answer::Cards(vec![visible[1]])).unwrap();
+
+    // no option of the shuffled stock reveals an id
+    let stock = cards(&interpreter.state, "stock", None);
+    let hidden = options(&mut interpreter);
+    assert_eq!(hidden, (0..6).map(CardChoice::Hidden).collect::<Vec<_>>());
+    assert!(!hidden.iter().any(|choice| matches!(choice, CardChoice::Card(_))));
+
+    interpreter.answer(Answer::Cards(vec![CardChoice::Hidden(0), CardChoice::Hidden(3)])).unwrap();
+    assert_eq!(interpreter.run().unwrap(), Step::Finished);
+    assert_eq!(cards(&interpreter.state, "discard", None)[..2], [stock[0], stock[3]]);
+  }
+
+  #[test]
+  fn test_free_deal() {
+    let mut interpreter = interpreter(
+      &format!("{}{}", SETUP, "
+        deal any from stock private to hand of all;
+      ")
+    );
+
+    assert_eq!(
+      interpreter.run().unwrap(),
+      Step::Input(InputRequest { player: String::from("P1"), options: Options::Amount(vec![1, 2]) })
+    );
+
+    interpreter.answer(Answer::Amount(1)).unwrap();
+    assert_eq!(interpreter.run().unwrap(), Step::Finished);
+    assert_eq!(cards(&interpreter.state, "stock", None).len(), 5);
+  }
+}
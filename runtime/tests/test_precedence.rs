@@ -0,0 +1,113 @@
+mod common;
+
+mod test {
+
+  use std::cmp::Ordering;
+
+  use syn::parse_str;
+
+  use ast::ast::*;
+
+  use runtime::precedence::*;
+  use runtime::state::*;
+
+  use crate::common::*;
+
+  // stock: Two, Queen, King, Ace of Hearts followed by Two, Queen, King, Ace of Spades
+  const SETUP: &str = "
+    players: (P1);
+    turnorder: (P1);
+    location stock on table;
+    card on stock: Suite(Hearts, Spades) for Rank(Two, Queen, King, Ace);
+    precedence RankOrder on Rank(Ace, Two, Queen, King);
+    precedence Trump (Rank(Two), Rank(Queen), Rank(King), Rank(Ace), Suite(Hearts));
+    pointmap Hearts (Suite(Hearts: 1), Rank(Queen: 12));
+  ";
+
+  fn card(state: &GameState, rank: &str, suite: &str) -> CardId {
+    state.cards
+      .iter()
+      .find(|card| card.value("Rank") == Some(rank) && card.value("Suite") == Some(suite))
+      .unwrap()
+      .id
+  }
+
+  #[test]
+  fn test_multi_key_precedence() {
+    let state = run(SETUP);
+
+    let two_of_hearts = card(&state, "Two", "Hearts");
+    let ace_of_hearts = card(&state, "Ace", "Hearts");
+    let ace_of_spades = card(&state, "Ace", "Spades");
+    let king_of_spades = card(&state, "King", "Spades");
+
+    // trump beats every other suite, then the rank decides
+    assert_eq!(state.compare_cards(two_of_hearts, ace_of_spades, "Trump"), Ok(Ordering::Greater));
+    assert_eq!(state.compare_cards(ace_of_hearts, two_of_hearts, "Trump"), Ok(Ordering::Greater));
+    assert_eq!(state.compare_cards(king_of_spades, ace_of_spades, "Trump"), Ok(Ordering::Less));
+
+    // RankOrder ignores the suite
+    assert_eq!(state.compare_cards(ace_of_hearts, ace_of_spades, "RankOrder"), Ok(Ordering::Equal));
+  }
+
+  #[test]
+  fn test_max_and_min_prec() {
+    let state = run(SETUP);
+
+    let position: CardPosition = parse_str("max(stock) using prec(Trump)").unwrap();
+    assert_eq!(state.eval_card(&position), Ok(card(&state, "Ace", "Hearts")));
+
+    // ties are won by the first card
+    let position: CardPosition = parse_str("min(stock) using prec(RankOrder)").unwrap();
+    assert_eq!(state.eval_card(&position), Ok(card(&state, "Ace", "Hearts")));
+  }
+
+  #[test]
+  fn test_point_map_pairs() {
+    let state = run(SETUP);
+
+    assert_eq!(state.points_of(card(&state, "Two", "Hearts"), "Hearts"), Ok(1));
+    assert_eq!(state.points_of(card(&state, "Queen", "Hearts"), "Hearts"), Ok(13));
+    assert_eq!(state.points_of(card(&state, "Queen", "Spades"), "Hearts"), Ok(12));
+    assert_eq!(state.points_of(card(&state, "King", "Spades"), "Hearts"), Ok(0));
+
+    let int: IntExpr = parse_str("sum of stock using Hearts").unwrap();
+    assert_eq!(state.eval_int(&int), Ok(28));
+  }
+
+  #[test]
+  fn test_wrap_around() {
+    let mut state = run(SETUP);
+    let filter: FilterExpr = parse_str("(same Suite and adjacent Rank using RankOrder)").unwrap();
+
+    let queen_king_ace = [
+      card(&state, "Queen", "Spades"),
+      card(&state, "King", "Spades"),
+      card(&state, "Ace", "Spades"),
+    ];
+    let king_ace_two = [
+      card(&state, "King", "Spades"),
+      card(&state, "Ace", "Spades"),
+      card(&state, "Two", "Spades"),
+    ];
+
+    assert!(!state.matches(&queen_king_ace, &filter).unwrap());
+    assert!(state.matches(&king_ace_two[1..], &filter).unwrap());
+
+    state.wrap_around.push(String::from("RankOrder"));
+
+    assert!(state.matches(&queen_king_ace, &filter).unwrap());
+    assert!(state.matches(&king_ace_two, &filter).unwrap());
+  }
+
+  #[test]
+  fn test_is_run() {
+    assert!(is_run(&[3, 1, 2], 13, false));
+    assert!(!is_run(&[1, 1, 2], 13, false));
+    assert!(!is_run(&[11, 12, 0], 13, false));
+    assert!(is_run(&[11, 12, 0], 13, true));
+    assert!(is_run(&[12, 0, 1], 13, true));
+    assert!(!is_run(&[12, 0, 2], 13, true));
+    assert!(is_run(&[0, 1, 2, 3], 4, true));
+  }
+}
@@ -0,0 +1,147 @@
+mod common;
+
+mod test {
+
+  use runtime::error::*;
+  use runtime::input::*;
+  use runtime::interpreter::*;
+  use runtime::replay::*;
+  use runtime::state::*;
+
+  use crate::common::*;
+
+  const GAME: &str = "
+    players: (P1, P2, P3);
+    random turnorder: (P1, P2, P3);
+    location hand on players all;
+    location (stock, discard) on table;
+    memory Bid on all;
+    card on stock:
+      Rank(Two, Three, Four, Five)
+        for Suite(Hearts, Spades);
+    shuffle stock;
+    deal 2 from stock private to hand of all;
+    bid range(<= 3) on Bid;
+    choose {
+      move top(hand) face up to discard;
+      or
+      cycle to next;
+    }
+  ";
+
+  /// Plays GAME with the seed, the current player bids 2 and takes the first choice.
+  fn play(seed: u64) -> Interpreter {
+    let mut interpreter = Interpreter::from_game_with_seed(game(GAME), seed).unwrap().with_diffs();
+
+    assert!(matches!(interpreter.run().unwrap(), Step::Input(_)));
+    interpreter.answer(Answer::Int(2)).unwrap();
+
+    let choice = match interpreter.run().unwrap() {
+      Step::Input(InputRequest { options: Options::Choice(options), .. }) => options[0],
+      step => panic!("expected a choice, got {:?}", step),
+    };
+    interpreter.answer(Answer::Transition(choice)).unwrap();
+    assert_eq!(interpreter.run().unwrap(), Step::Finished);
+
+    interpreter
+  }
+
+  #[test]
+  fn test_log() {
+    let interpreter = play(11);
+    let log = interpreter.log();
+
+    assert_eq!(log[0], Event::Seed(11));
+    let answers = log
+      .iter()
+      .filter(|event| matches!(event, Event::Answer { .. }))
+      .count();
+    assert_eq!(answers, 2);
+
+    // the diffs rebuild the final state
+    let mut state = GameState::default();
+    for event in log.iter() {
+      if let Event::Diff(changes) = event {
+        state.apply(changes);
+      }
+    }
+    assert_eq!(state, interpreter.state);
+  }
+
+  #[test]
+  fn test_replay() {
+    let played = play(5);
+
+    let replayed = replay(game(GAME), played.log()).unwrap();
+    assert_eq!(replayed.state, played.state);
+    assert_eq!(replayed.log(), played.log());
+  }
+
+  #[test]
+  fn test_diffs_are_opt_in() {
+    let mut interpreter = Interpreter::from_game_with_seed(game(GAME), 5).unwrap();
+    assert!(matches!(interpreter.run().unwrap(), Step::Input(_)));
+    interpreter.answer(Answer::Int(2)).unwrap();
+    interpreter.run().unwrap();
+
+    assert!(!interpreter.log().iter().any(|event| matches!(event, Event::Diff(_))));
+    let replayed = replay(game(GAME), interpreter.log()).unwrap();
+    assert_eq!(replayed.state, interpreter.state);
+    assert_eq!(replayed.log(), interpreter.log());
+  }
+
+  #[test]
+  fn test_rejected_answers_are_not_logged() {
+    let mut interpreter = Interpreter::from_game(game(GAME)).unwrap();
+
+    interpreter.run().unwrap();
+    let logged = interpreter.log().len();
+    assert_eq!(interpreter.answer(Answer::Int(7)), Err(RuntimeError::InvalidBid(7)));
+    assert_eq!(interpreter.log().len(), logged);
+  }
+
+  #[test]
+  fn test_divergent_game() {
+    let played = play(5);
+
+    let changed = GAME.replace("deal 2 from", "deal 1 from");
+    let divergence = replay(game(&changed), played.log()).unwrap_err();
+
+    // the deal is the first diff with private cards
+    let deal = played.log()
+      .iter()
+      .position(|event| match event {
+        Event::Diff(changes) => changes.iter().any(|change| {
+          matches!(change, Change::Card(Card { status: CardStatus::Private, .. }))
+        }),
+        _ => false,
+      })
+      .unwrap();
+    assert_eq!(divergence.index, deal);
+    assert!(divergence.found.is_some());
+  }
+
+  #[test]
+  fn test_divergent_answer() {
+    let played = play(5);
+
+    let mut log = played.log().to_vec();
+    let index = log
+      .iter()
+      .position(|event| matches!(event, Event::Answer { .. }))
+      .unwrap();
+    if let Event::Answer { answer, .. } = &mut log[index] {
+      *answer = Answer::Int(9);
+    }
+
+    let divergence = replay(game(GAME), &log).unwrap_err();
+    assert_eq!(divergence.index, index);
+    assert_eq!(divergence.found, None);
+    assert_eq!(divergence.error, Some(RuntimeError::InvalidBid(9)));
+
+    // a log of another seed diverges at the first shuffle
+    log = played.log().to_vec();
+    log[0] = Event::Seed(6);
+    assert!(replay(game(GAME), &log).is_err());
+  }
+}
@@ -0,0 +1,90 @@
+mod common;
+
+mod test {
+
+  use runtime::interpreter::*;
+  use runtime::rng::*;
+  use runtime::state::*;
+
+  use crate::common::*;
+
+  fn run(source: &str, seed: u64) -> GameState {
+    let mut interpreter = Interpreter::from_game_with_seed(game(source), seed).unwrap();
+    assert_eq!(interpreter.seed(), seed);
+
+    assert_eq!(interpreter.run().unwrap(), Step::Finished);
+
+    interpreter.state
+  }
+
+  const GAME: &str = "
+    players: (P1, P2, P3, P4);
+    random turnorder: (P1, P2, P3, P4);
+    location (stock, discard) on table;
+    card on stock:
+      Rank(Two, Three, Four, Five, Six, Seven, Eight, Nine)
+        for Suite(Hearts, Spades);
+    shuffle stock;
+  ";
+
+  #[test]
+  fn test_same_seed_same_game() {
+    let first = run(GAME, 42);
+    let second = run(GAME, 42);
+
+    assert_eq!(first.turnorder, second.turnorder);
+    assert_eq!(cards(&first, "stock", None), cards(&second, "stock", None));
+
+    let other = run(GAME, 7);
+    assert_ne!(cards(&first, "stock", None), cards(&other, "stock", None));
+  }
+
+  #[test]
+  fn test_shuffle_keeps_cards() {
+    let state = run(GAME, 1);
+
+    let mut stock = cards(&state, "stock", None);
+    assert_ne!(stock, (0..16).collect::<Vec<_>>());
+
+    stock.sort();
+    assert_eq!(stock, (0..16).collect::<Vec<_>>());
+  }
+
+  #[test]
+  fn test_shuffle_filtered_cards() {
+    let state = run(
+      "
+        players: (P1, P2);
+        turnorder: (P1, P2);
+        location stock on table;
+        card on stock:
+          Rank(Two, Three, Four, Five, Six, Seven, Eight, Nine)
+            for Suite(Hearts, Spades);
+        shuffle stock where Key(Suite == Hearts);
+      ",
+      3
+    );
+
+    // the hearts are the even cards, the spades keep their places
+    let stock = cards(&state, "stock", None);
+    for (place, card) in stock.iter().enumerate() {
+      assert_eq!(place % 2, card % 2);
+      if place % 2 == 1 {
+        assert_eq!(place, *card);
+      }
+    }
+    assert_ne!(stock, (0..16).collect::<Vec<_>>());
+  }
+
+  #[test]
+  fn test_rng_sequence() {
+    let mut rng = Rng::new(5);
+    let first = (0..4).map(|_| rng.next_u64()).collect::<Vec<_>>();
+
+    let mut rng = Rng::new(5);
+    let second = (0..4).map(|_| rng.next_u64()).collect::<Vec<_>>();
+
+    assert_eq!(first, second);
+    assert_eq!(rng.seed(), 5);
+  }
+}
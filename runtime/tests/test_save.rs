@@ -0,0 +1,98 @@
+mod common;
+
+mod test {
+
+  use runtime::error::*;
+  use runtime::input::*;
+  use runtime::interpreter::*;
+  use runtime::save::*;
+
+  use crate::common::*;
+
+  const GAME: &str = "
+    players: (P1, P2);
+    random turnorder: (P1, P2);
+    location hand on players all;
+    location (stock, discard) on table;
+    memory Bid on all;
+    card on stock:
+      Rank(Two, Three, Four)
+        for Suite(Hearts, Spades);
+    combo Pair where (size == 2 and same Rank);
+    shuffle stock;
+
+    stage Play for current until(2 times) {
+      move top(stock) private to hand;
+      bid range(<= 3) on Bid;
+      score memory Bid of current;
+    }
+
+    move Pair in hand face up to discard;
+  ";
+
+  /// Answers every bid with 1 and plays to the end.
+  fn finish(interpreter: &mut Interpreter) {
+    loop {
+      match interpreter.run().unwrap() {
+        Step::Input(_) => interpreter.answer(Answer::Int(1)).unwrap(),
+        Step::Finished => return,
+        step => panic!("unexpected step {:?}", step),
+      }
+    }
+  }
+
+  /// Starts the game and answers the first bid, the second bid is pending.
+  fn started() -> Interpreter {
+    let mut interpreter = Interpreter::from_game_with_seed(game(GAME), 9).unwrap();
+
+    assert!(matches!(interpreter.run().unwrap(), Step::Input(_)));
+    interpreter.answer(Answer::Int(1)).unwrap();
+    assert!(matches!(interpreter.run().unwrap(), Step::Input(_)));
+
+    interpreter
+  }
+
+  fn assert_resumes(saved: SavedGame) {
+    let mut played = started();
+    let mut resumed = Interpreter::load(game(GAME), saved).unwrap();
+    assert_eq!(resumed.current_state(), played.current_state());
+    assert_eq!(resumed.state, played.state);
+
+    finish(&mut played);
+    finish(&mut resumed);
+    assert_eq!(resumed.state, played.state);
+    assert_eq!(resumed.log(), played.log());
+  }
+
+  #[test]
+  fn test_formats() {
+    let saved = started().save();
+    assert_eq!(saved.combos, vec![String::from("Pair")]);
+
+    let ron = saved.to_ron().unwrap();
+    assert_eq!(SavedGame::from_ron(&ron).unwrap(), saved);
+    assert_resumes(SavedGame::from_ron(&ron).unwrap());
+
+    let json = saved.to_json().unwrap();
+    assert_eq!(SavedGame::from_json(&json).unwrap(), saved);
+    assert_resumes(SavedGame::from_json(&json).unwrap());
+
+    let bytes = saved.to_bincode().unwrap();
+    assert_eq!(SavedGame::from_bincode(&bytes).unwrap(), saved);
+    assert_resumes(SavedGame::from_bincode(&bytes).unwrap());
+  }
+
+  #[test]
+  fn test_invalid_saves() {
+    assert!(matches!(SavedGame::from_json("{"), Err(RuntimeError::InvalidSave(_))));
+    assert!(matches!(SavedGame::from_bincode(&[1, 2]), Err(RuntimeError::InvalidSave(_))));
+
+    let mut saved = started().save();
+    saved.combos.push(String::from("Flush"));
+    assert_eq!(Interpreter::load(game(GAME), saved).unwrap_err(), RuntimeError::UnknownCombo(String::from("Flush")));
+
+    let mut saved = started().save();
+    saved.current_state = -1;
+    assert_eq!(Interpreter::load(game(GAME), saved).unwrap_err(), RuntimeError::UnknownState(-1));
+  }
+}
@@ -0,0 +1,138 @@
+mod common;
+
+mod test {
+
+  use syn::parse_str;
+
+  use ast::ast::*;
+
+  use runtime::scoring::*;
+
+  use crate::common::*;
+
+  fn names(names: &[&str]) -> Vec<String> {
+    names.iter().map(|name| name.to_string()).collect()
+  }
+
+  const SETUP: &str = "
+    players: (P1, P2, P3, P4);
+    turnorder: (P1, P2, P3, P4);
+    memory Tricks on all;
+  ";
+
+  #[test]
+  fn test_score() {
+    let state = run(
+      &format!("{}{}", SETUP, "
+        score 5 of P1;
+        score 3 of (P1, P2);
+        score (0 - 2) of P3;
+        score 2 to Tricks of P4;
+        score 1 to Tricks of all;
+      ")
+    );
+
+    assert_eq!(state.score("P1"), Ok(8));
+    assert_eq!(state.score("P2"), Ok(3));
+    assert_eq!(state.score("P3"), Ok(-2));
+    assert_eq!(state.score("P4"), Ok(0));
+    assert_eq!(state.memory_int("Tricks", Some("P4")), Ok(3));
+    assert_eq!(state.memory_int("Tricks", Some("P1")), Ok(1));
+  }
+
+  #[test]
+  fn test_score_winners() {
+    let state = run(
+      &format!("{}{}", SETUP, "
+        score 5 of (P1, P3);
+        score 2 of P2;
+        winner is highest score;
+      ")
+    );
+    assert_eq!(state.winners, names(&["P1", "P3"]));
+    assert_eq!(state.standing(), vec![names(&["P1", "P3"]), names(&["P2"]), names(&["P4"])]);
+
+    let state = run(
+      &format!("{}{}", SETUP, "
+        score 5 of (P1, P3);
+        score 2 of P2;
+        winner is lowest score;
+      ")
+    );
+    assert_eq!(state.winners, names(&["P4"]));
+  }
+
+  #[test]
+  fn test_team_victory() {
+    let state = run(
+      &format!("{}{}", SETUP, "
+        team A: (P1, P3);
+        team B: (P2, P4);
+        score 5 of P1;
+        score 4 of (P2, P4);
+        winner is highest score;
+      ")
+    );
+
+    // B scores 8 against the 5 of A
+    assert_eq!(state.winners, names(&["P2", "P4"]));
+    assert_eq!(state.winning_teams(), names(&["B"]));
+  }
+
+  #[test]
+  fn test_positions() {
+    let game = format!("{}{}", SETUP, "
+      set P3 out of game successful;
+      set P1 out of game fail;
+      set P3 out of game fail;
+    ");
+
+    let state = run(&format!("{}{}", game, "winner is lowest position;"));
+    assert_eq!(state.player("P3").unwrap().finish, Some(Finish { position: 1, successful: true }));
+    assert_eq!(state.player("P1").unwrap().finish, Some(Finish { position: 2, successful: false }));
+    assert_eq!(state.position("P2"), Ok(2));
+    assert_eq!(state.position("P1"), Ok(4));
+    assert_eq!(state.winners, names(&["P3"]));
+    assert_eq!(state.standing(), vec![names(&["P3"]), names(&["P2", "P4"]), names(&["P1"])]);
+
+    // the eliminated player has the last position
+    let state = run(&format!("{}{}", game, "winner is highest position;"));
+    assert_eq!(state.winners, names(&["P1"]));
+  }
+
+  #[test]
+  fn test_eliminated_player_does_not_win() {
+    let state = run(
+      &format!("{}{}", SETUP, "
+        set P1 out of game fail;
+        winner is lowest position;
+      ")
+    );
+
+    // the players still in the game share the first position
+    assert_eq!(state.position("P1"), Ok(4));
+    assert_eq!(state.winners, names(&["P2", "P3", "P4"]));
+    assert_eq!(state.standing(), vec![names(&["P2", "P3", "P4"]), names(&["P1"])]);
+  }
+
+  #[test]
+  fn test_memory_and_player_winners() {
+    let state = run(
+      &format!("{}{}", SETUP, "
+        score 3 to Tricks of (P2, P3);
+        winner is highest Tricks;
+      ")
+    );
+    assert_eq!(state.winners, names(&["P2", "P3"]));
+
+    let mut state = run(
+      &format!("{}{}", SETUP, "
+        set P4 out of game fail;
+      ")
+    );
+    let winner_rule = WinnerRule::WinnerPlayerCollection(parse_str("playersin").unwrap());
+    state.winners = state.resolve_winners(&winner_rule).unwrap();
+    assert_eq!(state.winners, names(&["P1", "P2", "P3"]));
+    assert_eq!(state.standing(), vec![names(&["P1", "P2", "P3"]), names(&["P4"])]);
+  }
+}
@@ -0,0 +1,116 @@
+mod common;
+
+mod test {
+
+  use runtime::error::*;
+  use runtime::simulate::*;
+
+  use crate::common::*;
+
+  const GAME: &str = "
+    players: (P1, P2, P3, P4);
+    team Red: (P1, P3);
+    team Blue: (P2, P4);
+    turnorder: (P1, P2, P3, P4);
+    stage Play for current until(3 times) {
+      choose {
+        score 1 of current;
+        or
+        score 2 of current;
+      }
+      optional {
+        score 1 of current;
+      }
+      cycle to next;
+    }
+    winner is highest score;
+  ";
+
+  fn simulate(games: usize, seed: u64) -> Statistics {
+    Simulator { games, seed, ..Simulator::default() }.simulate(game(GAME)).unwrap()
+  }
+
+  #[test]
+  fn test_statistics() {
+    let statistics = simulate(200, 1);
+    assert_eq!(statistics.games, 200);
+    assert_eq!(statistics.finished, 200);
+    assert!(statistics.unfinished.is_empty());
+    assert!(statistics.errors.is_empty());
+
+    assert_eq!(statistics.turns.samples(), 200);
+    assert_eq!(statistics.steps.samples(), 200);
+    assert_eq!(statistics.stage_rounds.len(), 1);
+    let rounds = statistics.stage_rounds.values().next().unwrap();
+    assert_eq!(rounds.samples(), 200);
+    assert_eq!(rounds.min(), Some(3));
+    assert_eq!(rounds.max(), Some(3));
+    assert_eq!(statistics.turns.mean(), Some(4.0));
+
+    // every round takes one of the two branches and enters or skips the optional rule
+    assert_eq!(statistics.choices.len(), 2);
+    assert_eq!(statistics.choices.values().sum::<usize>(), 600);
+    assert!(statistics.choices.values().all(|count| *count > 0));
+    assert_eq!(statistics.optionals.len(), 1);
+    let optional = statistics.optionals.values().next().unwrap();
+    assert_eq!(optional.entered + optional.skipped, 600);
+
+    // ties have several winners
+    let wins: usize = statistics.wins.values().sum();
+    assert_eq!(statistics.seat_wins.iter().sum::<usize>(), wins);
+    assert!(wins >= 200);
+    assert_eq!(statistics.no_winner, 0);
+    assert_eq!(statistics.wins.len(), 4);
+    assert!(statistics.win_rate("P1") > 0.0);
+    assert!(statistics.seat_win_rate(0) > 0.0);
+    assert!(statistics.team_win_rate("Red") + statistics.team_win_rate("Blue") >= 1.0);
+  }
+
+  #[test]
+  fn test_same_seed_same_statistics() {
+    assert_eq!(simulate(50, 3), simulate(50, 3));
+    assert_ne!(simulate(50, 3).choices, simulate(50, 4).choices);
+  }
+
+  #[test]
+  fn test_unfinished_and_failed_games() {
+    let simulator = Simulator { games: 3, max_steps: 50, ..Simulator::default() };
+
+    let statistics = simulator.simulate(game("
+      players: (P1, P2);
+      turnorder: (P1, P2);
+      stage Play for current until(end) {
+        cycle to next;
+      }
+    ")).unwrap();
+    assert_eq!(statistics.unfinished.len(), 3);
+    assert_eq!(statistics.finished, 0);
+    assert_eq!(statistics.turns.mean(), None);
+
+    let statistics = simulator.simulate(game("
+      players: (P1, P2);
+      turnorder: (P1, P2);
+      cycle to P3;
+    ")).unwrap();
+    assert_eq!(statistics.errors.len(), 3);
+    assert_eq!(statistics.errors[0].1, RuntimeError::UnknownPlayer(String::from("P3")));
+  }
+
+  #[test]
+  fn test_large_free_quantity() {
+    // 2^52 ways to move any of the deck, the agents must not list them
+    let simulator = Simulator { games: 5, ..Simulator::default() };
+
+    let statistics = simulator.simulate(game("
+      players: (P1, P2);
+      turnorder: (P1, P2);
+      location (stock, discard) on table;
+      card on stock:
+        Rank(Two, Three, Four, Five, Six, Seven, Eight, Nine, Ten, Jack, Queen, King, Ace)
+          for Suite(Hearts, Spades, Diamonds, Clubs);
+      move any from stock face up to discard;
+    ")).unwrap();
+    assert_eq!(statistics.finished, 5);
+    assert!(statistics.errors.is_empty());
+  }
+}
@@ -0,0 +1,116 @@
+mod common;
+
+mod test {
+
+  use syn::parse_str;
+
+  use ast::ast::*;
+
+  use runtime::error::*;
+  use runtime::interpreter::*;
+  use runtime::state::*;
+
+  use crate::common::*;
+
+  const SETUP: &str = "
+    players: (P1, P2);
+    team Red: (P1, P2);
+    turnorder: (P1, P2);
+    location (hand, trash) on players all;
+    location tricks on teams (Red);
+    location (stock, discard) on table;
+    card on stock:
+      Rank(Two, Three, Four)
+        for Suite(Hearts, Spades);
+  ";
+
+  #[test]
+  fn test_create_locations() {
+    let state = run(SETUP);
+
+    assert_eq!(state.locations.len(), 7);
+    assert!(state.location_index("hand", &Owner::Player(String::from("P2"))).is_some());
+    assert!(state.location_index("tricks", &Owner::Team(String::from("Red"))).is_some());
+    assert!(state.location_index("stock", &Owner::Table).is_some());
+    assert!(state.location_index("stock", &Owner::Player(String::from("P1"))).is_none());
+  }
+
+  #[test]
+  fn test_create_cards() {
+    let state = run(SETUP);
+
+    assert_eq!(state.cards.len(), 6);
+
+    let stock = state.resolve_location("stock", None).unwrap();
+    assert_eq!(state.locations[stock].cards, vec![0, 1, 2, 3, 4, 5]);
+
+    let card = state.card(1).unwrap();
+    assert_eq!(card.value("Rank"), Some("Two"));
+    assert_eq!(card.value("Suite"), Some("Spades"));
+    assert_eq!(card.status, CardStatus::FaceDown);
+  }
+
+  #[test]
+  fn test_resolve_location() {
+    let state = run(SETUP);
+
+    let hand = state.resolve_location("hand", None).unwrap();
+    assert_eq!(state.locations[hand].owner, Owner::Player(String::from("P1")));
+
+    let hand = state.resolve_location("hand", Some("P2")).unwrap();
+    assert_eq!(state.locations[hand].owner, Owner::Player(String::from("P2")));
+
+    // team locations are reachable through the players of the team
+    let tricks = state.resolve_location("tricks", Some("P2")).unwrap();
+    assert_eq!(state.locations[tricks].owner, Owner::Team(String::from("Red")));
+
+    assert_eq!(
+      state.resolve_location("deck", None),
+      Err(RuntimeError::UnknownLocation(String::from("deck of P1")))
+    );
+  }
+
+  #[test]
+  fn test_cardset_conditions() {
+    let state = run(
+      &format!("{}{}", SETUP, "
+        if (stock is not empty) {
+          end game with winner P1;
+        }
+      ")
+    );
+    assert_eq!(state.winners, vec![String::from("P1")]);
+
+    let state = run(
+      &format!("{}{}", SETUP, "
+        if (Suite of bottom(stock) == Spades) {
+          end game with winner P2;
+        }
+      ")
+    );
+    assert_eq!(state.winners, vec![String::from("P2")]);
+
+    let state = run(
+      &format!("{}{}", SETUP, "
+        if (hand of all is empty) {
+          end game with winner P2;
+        }
+      ")
+    );
+    assert_eq!(state.winners, vec![String::from("P2")]);
+  }
+
+  #[test]
+  fn test_duplicate_location() {
+    let game: Game = parse_str(
+      "
+        players: (P1, P2);
+        location hand on players all;
+        location hand on players (P2);
+      "
+    ).unwrap();
+    let mut interpreter = Interpreter::from_game(game).unwrap();
+
+    assert_eq!(interpreter.run(), Err(RuntimeError::DuplicateLocation(String::from("hand"))));
+  }
+}
@@ -0,0 +1,127 @@
+mod common;
+
+mod test {
+
+  use runtime::error::*;
+  use runtime::interpreter::*;
+  use runtime::input::*;
+  use runtime::state::*;
+
+  use crate::common::*;
+
+  fn tokens(state: &GameState, location: &str, player: Option<&str>) -> usize {
+    state.tokens_on(state.resolve_location(location, player).unwrap())
+  }
+
+  const SETUP: &str = "
+    players: (P1, P2, P3);
+    turnorder: (P1, P2, P3);
+    location bank on players all;
+    location pot on table;
+    token 10 Chip on bank;
+  ";
+
+  #[test]
+  fn test_create_tokens() {
+    let state = run(SETUP);
+
+    assert_eq!(tokens(&state, "bank", Some("P2")), 10);
+    assert_eq!(tokens(&state, "pot", None), 0);
+
+    let bank = state.resolve_location("bank", Some("P3")).unwrap();
+    assert_eq!(state.tokens_of(bank, "Chip"), 10);
+    assert_eq!(state.tokens_of(bank, "Coin"), 0);
+  }
+
+  #[test]
+  fn test_place() {
+    // everybody pays an ante, P2 raises
+    let state = run(
+      &format!("{}{}", SETUP, "
+        place 2 from bank of (P1, P2, P3) to pot;
+        place 3 from bank of P2 to pot;
+      ")
+    );
+
+    assert_eq!(tokens(&state, "pot", None), 9);
+    assert_eq!(tokens(&state, "bank", Some("P1")), 8);
+    assert_eq!(tokens(&state, "bank", Some("P2")), 5);
+
+    // the pot is paid out to P3
+    let state = run(
+      &format!("{}{}", SETUP, "
+        place 4 from bank of P1 to pot;
+        place pot to bank of P3;
+      ")
+    );
+    assert_eq!(tokens(&state, "pot", None), 0);
+    assert_eq!(tokens(&state, "bank", Some("P3")), 14);
+  }
+
+  #[test]
+  fn test_split_and_pair() {
+    let state = run(
+      &format!("{}{}", SETUP, "
+        token 6 Chip on pot;
+        place 2 from pot to bank of (P1, P2, P3);
+        place 1 from bank of (P1, P2) to bank of (P2, P3);
+      ")
+    );
+
+    assert_eq!(tokens(&state, "pot", None), 0);
+    assert_eq!(tokens(&state, "bank", Some("P1")), 11);
+    assert_eq!(tokens(&state, "bank", Some("P2")), 12);
+    assert_eq!(tokens(&state, "bank", Some("P3")), 13);
+  }
+
+  #[test]
+  fn test_split_all() {
+    let state = run(
+      &format!("{}{}", SETUP, "
+        token 10 Chip on pot;
+        place pot to bank of (P1, P2, P3);
+      ")
+    );
+
+    // every bank gets a third, the remainder stays in the pot
+    assert_eq!(tokens(&state, "pot", None), 1);
+    assert_eq!(tokens(&state, "bank", Some("P1")), 13);
+    assert_eq!(tokens(&state, "bank", Some("P2")), 13);
+    assert_eq!(tokens(&state, "bank", Some("P3")), 13);
+  }
+
+  #[test]
+  fn test_not_enough_tokens() {
+    let mut interpreter = interpreter(
+      &format!("{}{}", SETUP, "
+        place 6 from bank of P1 to pot;
+        place 6 from bank of (P1, P2) to pot;
+      ")
+    );
+
+    assert_eq!(interpreter.run(), Err(RuntimeError::NotEnoughTokens { needed: 6, available: 4 }));
+
+    // nothing was moved by the failing rule
+    assert_eq!(tokens(&interpreter.state, "bank", Some("P2")), 10);
+    assert_eq!(tokens(&interpreter.state, "pot", None), 6);
+  }
+
+  #[test]
+  fn test_free_amount() {
+    let mut interpreter = interpreter(
+      &format!("{}{}", SETUP, "
+        place range(<= 3) from bank of current to pot;
+      ")
+    );
+
+    assert_eq!(
+      interpreter.run().unwrap(),
+      Step::Input(InputRequest { player: String::from("P1"), options: Options::Amount(vec![0, 1, 2, 3]) })
+    );
+    assert_eq!(interpreter.answer(Answer::Amount(4)), Err(RuntimeError::InvalidQuantity));
+
+    interpreter.answer(Answer::Amount(3)).unwrap();
+    assert_eq!(interpreter.run().unwrap(), Step::Finished);
+    assert_eq!(tokens(&interpreter.state, "pot", None), 3);
+  }
+}
@@ -0,0 +1,105 @@
+mod common;
+
+mod test {
+
+  use runtime::error::*;
+
+  use crate::common::*;
+
+  const SETUP: &str = "
+    players: (P1, P2, P3, P4);
+    turnorder: (P1, P2, P3, P4);
+  ";
+
+  #[test]
+  fn test_next_and_previous() {
+    let state = run(SETUP);
+
+    assert_eq!(player(&state, "next"), Ok(String::from("P2")));
+    assert_eq!(player(&state, "previous"), Ok(String::from("P4")));
+    assert_eq!(player(&state, "turnorder(2)"), Ok(String::from("P3")));
+    assert_eq!(
+      player(&state, "turnorder(4)"),
+      Err(RuntimeError::IndexOutOfRange { index: 4, len: 4 })
+    );
+  }
+
+  #[test]
+  fn test_skip_out_of_game() {
+    let state = run(
+      &format!("{}{}", SETUP, "
+        set P2 out of game successful;
+        set P4 out of game fail;
+      ")
+    );
+
+    assert_eq!(player(&state, "next"), Ok(String::from("P3")));
+    assert_eq!(player(&state, "previous"), Ok(String::from("P3")));
+    assert_eq!(player(&state, "turnorder(1)"), Ok(String::from("P3")));
+  }
+
+  #[test]
+  fn test_skip_out_of_stage() {
+    let state = run(
+      &format!("{}{}", SETUP, "
+        stage Play for current until(3 times) {
+          if (stageroundcounter == 0) {
+            set P2 out of stage;
+          }
+          cycle to next;
+        }
+      ")
+    );
+
+    // P1 -> P3 -> P4 -> P1, P2 is back once the stage is left
+    assert_eq!(state.current_player().unwrap(), "P1");
+    assert_eq!(player(&state, "next"), Ok(String::from("P2")));
+  }
+
+  #[test]
+  fn test_only_current_left() {
+    let state = run(
+      &format!("{}{}", SETUP, "
+        set (P2, P3, P4) out of game fail;
+      ")
+    );
+
+    assert_eq!(player(&state, "next"), Ok(String::from("P1")));
+    assert_eq!(player(&state, "competitor"), Err(RuntimeError::NoCompetitor));
+  }
+
+  #[test]
+  fn test_competitor() {
+    let state = run(
+      "
+        players: (P1, P2);
+        turnorder: (P1, P2);
+      "
+    );
+    assert_eq!(player(&state, "competitor"), Ok(String::from("P2")));
+
+    // the next player outside of the own team
+    let state = run(
+      &format!("{}{}", SETUP, "
+        team A: (P1, P2);
+        team B: (P3, P4);
+      ")
+    );
+    assert_eq!(player(&state, "competitor"), Ok(String::from("P3")));
+  }
+
+  #[test]
+  fn test_random_turnorder() {
+    let state = run(
+      "
+        players: (P1, P2, P3, P4);
+        random turnorder: (P1, P2, P3, P4);
+      "
+    );
+
+    let mut turnorder = state.turnorder.clone();
+    turnorder.sort();
+    assert_eq!(turnorder, vec!["P1", "P2", "P3", "P4"]);
+    assert_eq!(state.current_player().unwrap(), state.turnorder[0]);
+  }
+}
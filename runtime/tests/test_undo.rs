@@ -0,0 +1,125 @@
+mod common;
+
+mod test {
+
+  use runtime::error::*;
+  use runtime::input::*;
+  use runtime::interpreter::*;
+  use runtime::replay::*;
+  use runtime::state::*;
+
+  use crate::common::*;
+
+  const GAME: &str = "
+    players: (P1, P2);
+    turnorder: (P1, P2);
+    location hand on players all;
+    location stock on table;
+    memory Bid on all;
+    card on stock:
+      Rank(Two, Three, Four, Five, Six, Seven, Eight, Nine)
+        for Suite(Hearts, Spades);
+    bid range(<= 3) on Bid;
+    shuffle stock;
+    deal 2 from stock private to hand of all;
+    bid range(<= 3) on Bid;
+  ";
+
+  fn stock(state: &GameState) -> Vec<CardId> {
+    state.locations[state.resolve_location("stock", None).unwrap()].cards.clone()
+  }
+
+  /// Answers the pending bid and runs to the next input.
+  fn bid(interpreter: &mut Interpreter, bid: i32) -> Step {
+    interpreter.answer(Answer::Int(bid)).unwrap();
+    interpreter.run().unwrap()
+  }
+
+  #[test]
+  fn test_undo_redo() {
+    let mut interpreter = Interpreter::from_game_with_seed(game(GAME), 4).unwrap();
+    assert!(matches!(interpreter.run().unwrap(), Step::Input(_)));
+    let start = interpreter.state.clone();
+
+    assert!(matches!(bid(&mut interpreter, 2), Step::Input(_)));
+    let played = interpreter.clone();
+    assert_eq!(interpreter.decisions().len(), 1);
+    assert_eq!(interpreter.decisions()[0].player, "P1");
+    assert_eq!(interpreter.decisions()[0].answer, Answer::Int(2));
+
+    // the bid is pending again, only the generator differs from the start
+    interpreter.undo().unwrap();
+    assert!(interpreter.decisions().is_empty());
+    assert!(matches!(interpreter.pending_input().unwrap(), Some(InputRequest { options: Options::Bid(_), .. })));
+    assert_eq!(GameState { rng: start.rng.clone(), ..interpreter.state.clone() }, start);
+    assert_eq!(interpreter.undo(), Err(RuntimeError::NothingToUndo));
+
+    interpreter.redo().unwrap();
+    assert_eq!(interpreter.state, played.state);
+    assert_eq!(interpreter.current_state(), played.current_state());
+
+    // the log is append-only, the undone events stay and the undo and redo are logged after them
+    assert!(interpreter.log().starts_with(played.log()));
+    assert!(matches!(
+      &interpreter.log()[played.log().len()..],
+      [Event::Undo, Event::Reseed(_), Event::Redo]
+    ));
+    assert_eq!(interpreter.redo(), Err(RuntimeError::NothingToRedo));
+  }
+
+  #[test]
+  fn test_undo_does_not_repeat_the_shuffle() {
+    let mut interpreter = Interpreter::from_game_with_seed(game(GAME), 4).unwrap();
+    interpreter.run().unwrap();
+    bid(&mut interpreter, 2);
+    let shuffled = stock(&interpreter.state);
+
+    // the same answer after an undo shuffles again
+    interpreter.undo().unwrap();
+    bid(&mut interpreter, 2);
+    assert_ne!(stock(&interpreter.state), shuffled);
+
+    // the replay undoes the first branch as well
+    let replayed = replay(game(GAME), interpreter.log()).unwrap();
+    assert_eq!(replayed.state, interpreter.state);
+    assert_eq!(replayed.log(), interpreter.log());
+  }
+
+  #[test]
+  fn test_undo_to() {
+    let mut interpreter = Interpreter::from_game_with_seed(game(GAME), 4).unwrap().with_diffs();
+    interpreter.run().unwrap();
+    bid(&mut interpreter, 2);
+    assert_eq!(bid(&mut interpreter, 3), Step::Finished);
+    let finished = interpreter.clone();
+
+    assert_eq!(interpreter.undo_to(3), Err(RuntimeError::UnknownDecision(3)));
+    interpreter.undo_to(0).unwrap();
+    assert!(interpreter.decisions().is_empty());
+    assert!(!interpreter.state.game_over);
+
+    interpreter.redo().unwrap();
+    interpreter.redo().unwrap();
+    assert_eq!(interpreter.state, finished.state);
+    assert!(interpreter.log().starts_with(finished.log()));
+
+    // the diffs follow the undos and redos
+    let mut state = GameState::default();
+    for event in interpreter.log().iter() {
+      if let Event::Diff(changes) = event {
+        state.apply(changes);
+      }
+    }
+    assert_eq!(GameState { combos: Vec::new(), ..interpreter.state.clone() }, state);
+
+    let replayed = replay(game(GAME), interpreter.log()).unwrap();
+    assert_eq!(replayed.state, interpreter.state);
+    assert_eq!(replayed.log(), interpreter.log());
+
+    // a new decision drops the undone ones
+    interpreter.undo().unwrap();
+    assert_eq!(bid(&mut interpreter, 1), Step::Finished);
+    assert_eq!(interpreter.decisions().len(), 2);
+    assert_eq!(interpreter.redo(), Err(RuntimeError::NothingToRedo));
+  }
+}
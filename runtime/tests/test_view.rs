@@ -0,0 +1,96 @@
+mod common;
+
+mod test {
+
+  use runtime::state::*;
+  use runtime::view::*;
+
+  use crate::common::*;
+
+  fn known(view: &PlayerView, location: &str, owner: Owner) -> Vec<CardId> {
+    view.location(location, &owner)
+      .unwrap()
+      .cards
+      .iter()
+      .filter_map(|card| match card {
+        CardView::Known(card) => Some(card.id),
+        CardView::Unknown => None,
+      })
+      .collect()
+  }
+
+  fn size(view: &PlayerView, location: &str, owner: Owner) -> usize {
+    view.location(location, &owner).unwrap().cards.len()
+  }
+
+  const TEAMS: &str = "
+    team Red: (P1, P2);
+    location tricks on teams (Red);
+  ";
+
+  #[test]
+  fn test_private_cards() {
+    let state = run(
+      &format!("{}{}{}", SETUP, TEAMS, "
+        deal 1 from stock private to hand of all;
+        move top(stock) private to tricks;
+      ")
+    );
+
+    let view = state.view("P1").unwrap();
+    assert_eq!(known(&view, "hand", Owner::Player(String::from("P1"))).len(), 1);
+    assert!(known(&view, "hand", Owner::Player(String::from("P2"))).is_empty());
+    assert_eq!(size(&view, "hand", Owner::Player(String::from("P2"))), 1);
+    assert_eq!(known(&view, "tricks", Owner::Team(String::from("Red"))).len(), 1);
+
+    let view = state.view("P3").unwrap();
+    assert!(known(&view, "tricks", Owner::Team(String::from("Red"))).is_empty());
+    assert_eq!(size(&view, "tricks", Owner::Team(String::from("Red"))), 1);
+  }
+
+  #[test]
+  fn test_face_up_and_face_down() {
+    let state = run(
+      &format!("{}{}{}", SETUP, TEAMS, "
+        move 2 from stock face up to discard;
+        flip top(discard) to face down;
+      ")
+    );
+
+    for player in ["P1", "P2", "P3"] {
+      let view = state.view(player).unwrap();
+      assert_eq!(known(&view, "discard", Owner::Table), vec![1]);
+      assert_eq!(size(&view, "discard", Owner::Table), 2);
+      assert!(known(&view, "stock", Owner::Table).is_empty());
+      assert_eq!(size(&view, "stock", Owner::Table), 6);
+    }
+
+    assert_eq!(state.view("P1").unwrap().location("discard", &Owner::Table).unwrap().cards[0], CardView::Unknown);
+    assert!(state.view("P4").is_err());
+  }
+
+  #[test]
+  fn test_memory_cards() {
+    let state = run(
+      &format!("{}{}{}", SETUP, TEAMS, "
+        memory Seen on table;
+        move 2 from stock private to hand of P1;
+        Seen is cards hand of P1;
+      ")
+    );
+
+    let memory_cards = |player: &str| {
+      let view = state.view(player).unwrap();
+      let memory = view.memories.iter().find(|memory| memory.name == "Seen").unwrap();
+      match &memory.value {
+        Some(MemoryValueView::Cards(cards)) => cards.clone(),
+        value => panic!("expected cards, got {:?}", value),
+      }
+    };
+
+    // the owner sees the stored cards, everybody else only their number
+    assert!(memory_cards("P1").iter().all(|card| matches!(card, CardView::Known(_))));
+    assert_eq!(memory_cards("P1").len(), 2);
+    assert_eq!(memory_cards("P2"), vec![CardView::Unknown, CardView::Unknown]);
+  }
+}
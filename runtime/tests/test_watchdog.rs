@@ -0,0 +1,123 @@
+mod common;
+
+mod test {
+
+  use runtime::error::*;
+  use runtime::interpreter::*;
+  use runtime::simulate::*;
+  use runtime::watchdog::*;
+
+  use crate::common::*;
+
+  fn stall(source: &str, watchdog: &Watchdog) -> Stall {
+    let mut interpreter = interpreter(source);
+
+    match interpreter.run_watched(watchdog) {
+      Err(RuntimeError::Stalled(stall)) => {
+        assert_eq!(stall.state, interpreter.current_state());
+        *stall
+      },
+      result => panic!("expected a stall, got {:?}", result),
+    }
+  }
+
+  const LOOP: &str = "
+    stage Play for current until(3 == 2) {
+      cycle to next;
+    }
+  ";
+
+  #[test]
+  fn test_loop() {
+    let stall = stall(&format!("{}{}", SETUP, LOOP), &Watchdog::default());
+
+    assert_eq!(stall.reason, StallReason::Loop { repeats: 101 });
+    assert_eq!(stall.stage, Some(String::from("Play")));
+    assert!(stall.to_string().starts_with("loop"));
+  }
+
+  #[test]
+  fn test_counted_rounds_are_no_loop() {
+    let mut interpreter = Interpreter::from_game(game(&format!("{}{}", SETUP, "
+      stage Play for current until(300 times) {
+        cycle to next;
+      }
+    "))).unwrap();
+
+    assert_eq!(interpreter.run_watched(&Watchdog::default()), Ok(Step::Finished));
+  }
+
+  #[test]
+  fn test_read_rounds_are_no_loop() {
+    let mut interpreter = Interpreter::from_game(game(&format!("{}{}", SETUP, "
+      stage Play for current until(stageroundcounter == 150) {
+        cycle to current;
+      }
+    "))).unwrap();
+
+    assert_eq!(interpreter.run_watched(&Watchdog::default()), Ok(Step::Finished));
+  }
+
+  #[test]
+  fn test_rounds_read_by_a_combo_are_no_loop() {
+    let mut interpreter = Interpreter::from_game(game(&format!("{}{}", SETUP, "
+      combo Late where size == (stageroundcounter - 400);
+      stage Play for current until(Late in stock is not empty) {
+        cycle to current;
+      }
+    "))).unwrap();
+
+    assert_eq!(interpreter.run_watched(&Watchdog::default()), Ok(Step::Finished));
+  }
+
+  #[test]
+  fn test_large_free_quantity_is_no_stall() {
+    let mut interpreter = Interpreter::from_game(game("
+      players: (P1, P2);
+      turnorder: (P1, P2);
+      location (stock, discard) on table;
+      card on stock:
+        Rank(Two, Three, Four, Five, Six, Seven, Eight, Nine, Ten, Jack, Queen, King, Ace)
+          for Suite(Hearts, Spades);
+      move any from stock face up to discard;
+    ")).unwrap();
+
+    assert!(matches!(interpreter.run_watched(&Watchdog::default()), Ok(Step::Input(_))));
+  }
+
+  #[test]
+  fn test_no_legal_action() {
+    let stall = stall(
+      &format!("{}{}", SETUP, "
+        stage Play for current until(end) {
+          move 3 from stock face up to discard;
+        }
+      "),
+      &Watchdog::default()
+    );
+
+    assert_eq!(
+      stall.reason,
+      StallReason::NoLegalAction(Some(RuntimeError::NotEnoughCards { needed: 3, available: 2 }))
+    );
+    assert_eq!(stall.stage, Some(String::from("Play")));
+  }
+
+  #[test]
+  fn test_step_budget() {
+    let stall = stall(&format!("{}{}", SETUP, LOOP), &Watchdog { max_steps: 20, ..Watchdog::default() });
+
+    assert_eq!(stall.reason, StallReason::StepBudget(20));
+    assert_eq!(stall.steps, 20);
+  }
+
+  #[test]
+  fn test_simulated_stalls() {
+    let simulator = Simulator { games: 2, ..Simulator::default() };
+    let statistics = simulator.simulate(game(&format!("{}{}", SETUP, LOOP))).unwrap();
+
+    assert_eq!(statistics.stalls.len(), 2);
+    assert!(statistics.stalls.iter().all(|(_, stall)| matches!(stall.reason, StallReason::Loop { .. })));
+    assert!(statistics.errors.is_empty());
+  }
+}